@@ -0,0 +1,68 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use isaac::{Arena, NodeId, SoaArena, Tree};
+
+/// The number of nodes in the benchmarked trees.
+const NODES: usize = 100_000;
+
+/// A node payload the size of a typical syntax node with its span and type.
+#[derive(Clone)]
+struct Payload([u64; 16]);
+
+/// Builds a tree where every node is the child of a pseudo-random earlier
+/// node, so that parents are spread over the whole arena.
+fn build() -> Arena<Payload> {
+    let mut arena = Arena::new();
+    let root = arena.insert(Payload([0; 16]));
+    let mut seed = 1u64;
+
+    for i in 1..NODES {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let parent = NodeId::from((seed >> 33) as usize % i);
+        let id = arena.insert(Payload([i as u64; 16]));
+        parent.add_child(&mut arena, id);
+    }
+
+    arena.set_root(root);
+    arena
+}
+
+/// Counts the ancestors of every node, walking up through its parents.
+fn depth_sum<A: Tree>(tree: &A) -> usize {
+    (0..NODES).map(|index| tree.ancestors(NodeId::from(index)).count()).sum()
+}
+
+/// Sums the first word of every payload in the subtree starting at `root`,
+/// visiting it in pre-order.
+fn preorder_sum<A: Tree<Data = Payload>>(tree: &A, root: NodeId) -> u64 {
+    let mut sum = 0;
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        sum += tree.data(id).unwrap().0[0];
+        stack.extend(tree.children(id).iter().rev());
+    }
+    sum
+}
+
+fn parent_walks(c: &mut Criterion) {
+    let arena = build();
+    let soa = SoaArena::from(build());
+    let mut group = c.benchmark_group("parent_walks");
+
+    group.bench_function("arena", |b| b.iter(|| black_box(depth_sum(&arena))));
+    group.bench_function("soa", |b| b.iter(|| black_box(depth_sum(&soa))));
+    group.finish();
+}
+
+fn preorder(c: &mut Criterion) {
+    let arena = build();
+    let soa = SoaArena::from(build());
+    let root = NodeId::from(0);
+    let mut group = c.benchmark_group("preorder");
+
+    group.bench_function("arena", |b| b.iter(|| black_box(preorder_sum(&arena, root))));
+    group.bench_function("soa", |b| b.iter(|| black_box(preorder_sum(&soa, root))));
+    group.finish();
+}
+
+criterion_group!(benches, parent_walks, preorder);
+criterion_main!(benches);
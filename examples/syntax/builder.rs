@@ -4,6 +4,7 @@ use crate::token::*;
 use crate::node::*;
 use crate::cache::Cache;
 use isaac::{Arena, Node, NodeId};
+use std::fmt;
 use std::rc::Rc;
 
 #[macro_export]
@@ -34,6 +35,31 @@ pub enum Syntax {
     Token(SyntaxToken),
 }
 
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Syntax::Node(node) => match node.kind() {
+                SyntaxNodeKind::Expr(Expr::Binary) => write!(f, "binary"),
+                SyntaxNodeKind::Expr(Expr::Group) => write!(f, "group"),
+                SyntaxNodeKind::Expr(Expr::Unary) => write!(f, "unary"),
+            },
+            Syntax::Token(token) => write!(f, "token {}", token.text()),
+        }
+    }
+}
+
+/// Returns the kind of a syntax element, for use with `isaac::Selector`.
+pub fn kind(syntax: &Syntax) -> &str {
+    match syntax {
+        Syntax::Node(node) => match node.kind() {
+            SyntaxNodeKind::Expr(Expr::Binary) => "Binary",
+            SyntaxNodeKind::Expr(Expr::Group) => "Group",
+            SyntaxNodeKind::Expr(Expr::Unary) => "Unary",
+        },
+        Syntax::Token(_) => "Token",
+    }
+}
+
 pub struct SyntaxBuilder {
     arena: Arena<Syntax>,
     raw_token_cache: Cache<String, Rc<RawSyntaxTokenData>>,
@@ -44,6 +70,14 @@ impl SyntaxBuilder {
         Self { arena, raw_token_cache: Cache::new() }
     }
 
+    pub fn arena(&self) -> &Arena<Syntax> {
+        &self.arena
+    }
+
+    pub fn arena_mut(&mut self) -> &mut Arena<Syntax> {
+        &mut self.arena
+    }
+
     pub fn node_at(&self, id: NodeId) -> Option<&Node<Syntax>> {
         self.arena.node_at(id)
     }
@@ -52,9 +86,13 @@ impl SyntaxBuilder {
         self.arena.nodes()
     }
 
+    pub fn to_sexpr(&self, root: NodeId) -> String {
+        self.arena.to_sexpr(root)
+    }
+
     pub fn make_token(&mut self, text: &str, start: usize) -> NodeId {
         let raw = self.raw_token_cache.lookup(text.into(), Rc::new(lex(text)));
-        let token = SyntaxToken::new(Rc::clone(&raw), start, text.len());
+        let token = SyntaxToken::new(Rc::clone(raw), start, text.len());
         self.arena.insert(Syntax::Token(token))
     }
 
@@ -93,6 +131,11 @@ impl SyntaxBuilder {
         *group_expr
             .add_child(&mut self.arena, inner_expr)
     }
+
+    pub fn wrap_in_group_expr(&mut self, expr: NodeId) -> NodeId {
+        let node = SyntaxNode::new(SyntaxNodeKind::Expr(Expr::Group));
+        self.arena.cursor(expr).wrap_in(Syntax::Node(node))
+    }
 }
 
 fn lex(s: &str) -> RawSyntaxTokenData {
@@ -230,4 +273,72 @@ mod tests {
             builder.nodes()
         });
     }
+
+    #[test]
+    fn test_syntax_builder_sexpr() {
+        // 1 + -(a)
+        let mut builder = SyntaxBuilder::new(Arena::new());
+
+        let root = make!(builder => binary {
+            make!(builder => token { "1", 0 }),
+            make!(builder => token { "+", 2 }),
+            make!(builder => unary {
+                make!(builder => token { "-", 4 }),
+                make!(builder => group {
+                    make!(builder => token { "a", 6 }),
+                }),
+            }),
+        });
+
+        assert_eq!(
+            builder.to_sexpr(root),
+            "(binary (token 1) (token +) (unary (token -) (group (token a))))",
+        );
+    }
+
+    #[test]
+    fn test_syntax_builder_wrap_in_group() {
+        // 1 + 2 * 3
+        let mut builder = SyntaxBuilder::new(Arena::new());
+
+        let root = make!(builder => binary {
+            make!(builder => token { "1", 0 }),
+            make!(builder => token { "+", 2 }),
+            make!(builder => binary {
+                make!(builder => token { "2", 4 }),
+                make!(builder => token { "*", 6 }),
+                make!(builder => token { "3", 8 }),
+            }),
+        });
+
+        // 1 + (2 * 3)
+        let rhs = builder.arena().node_at(root).unwrap().children()[2];
+        let group = builder.wrap_in_group_expr(rhs);
+
+        assert_eq!(rhs.parent(builder.arena()), Some(group));
+        assert_eq!(
+            builder.to_sexpr(root),
+            "(binary (token 1) (token +) (group (binary (token 2) (token *) (token 3))))",
+        );
+    }
+
+    #[test]
+    fn test_syntax_builder_sexpr_round_trip() {
+        // (a)
+        let mut builder = SyntaxBuilder::new(Arena::new());
+
+        let lparen = builder.make_token("(", 0);
+        let root = make!(builder => group {
+            make!(builder => token { "a", 1 }),
+        });
+        let rparen = builder.make_token(")", 2);
+        root.insert_child(builder.arena_mut(), 0, lparen);
+        root.add_child(builder.arena_mut(), rparen);
+
+        let text = builder.to_sexpr(root);
+        assert_eq!(text, r#"(group ("token (") (token a) ("token )"))"#);
+
+        let parsed = Arena::from_sexpr(&text, |label| Ok::<_, ()>(label.to_string())).unwrap();
+        assert_eq!(parsed.to_sexpr(parsed.root().unwrap()), text);
+    }
 }
@@ -7,7 +7,7 @@ use std::hash::Hash;
 macro_rules! cache {
     ( $( $key:expr => $value:expr ),* ) => {
         {
-            use crate::cache::Cache;
+            use $crate::cache::Cache;
             let mut cache = Cache::new();
             $(
                 cache.insert($key, $value);
@@ -41,7 +41,7 @@ impl<K, V> Cache<K, V> where K: Hash + Eq + Clone {
     /// with the given arguments. Refer to its documentation for more
     /// information.
     pub fn get(&self, key: &K) -> Option<&V> {
-        self.0.get(&key)
+        self.0.get(key)
     }
 
     /// Looks up a value with the given key and, if found, returns the value,
@@ -107,7 +107,7 @@ impl<K, V> Cache<K, V> where K: Hash + Eq + Clone {
     /// assert_eq!(cache.get(&"ten".to_string()), Some(&10));
     /// ```
     pub fn lookup_with<F: FnOnce() -> V>(&mut self, key: K, create_value: F) -> &V {
-        let value = self.0.entry(key.clone()).or_insert_with(|| create_value());
+        let value = self.0.entry(key.clone()).or_insert_with(create_value);
         value
     }
 
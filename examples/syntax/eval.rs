@@ -0,0 +1,97 @@
+use crate::builder::Syntax;
+use crate::node::*;
+use crate::token::*;
+use isaac::{Arena, NodeId};
+use std::collections::HashMap;
+
+/// The value of a node while folding an expression tree.
+enum Value {
+    Number(i64),
+    Symbol(Symbol),
+    Invalid,
+}
+
+/// Evaluates the expression rooted at `root`, looking up identifiers in
+/// `vars`.
+///
+/// Returns `None` if the expression is malformed, refers to an unknown
+/// identifier, overflows or divides by zero.
+pub fn eval(arena: &Arena<Syntax>, root: NodeId, vars: &HashMap<&str, i64>) -> Option<i64> {
+    let value = arena.fold(root, |node, children| match node.data() {
+        Syntax::Token(token) => match token.kind() {
+            SyntaxTokenKind::Literal(Literal::Integer) => {
+                token.text().parse().map(Value::Number).unwrap_or(Value::Invalid)
+            },
+            SyntaxTokenKind::Identifier => {
+                vars.get(token.text()).map_or(Value::Invalid, |&n| Value::Number(n))
+            },
+            SyntaxTokenKind::Symbol(symbol) => Value::Symbol(symbol),
+            _ => Value::Invalid,
+        },
+        Syntax::Node(node) => match (node.kind(), children.as_slice()) {
+            (SyntaxNodeKind::Expr(Expr::Group), [Value::Number(n)]) => {
+                Value::Number(*n)
+            },
+            (SyntaxNodeKind::Expr(Expr::Unary), [Value::Symbol(Symbol::Minus), Value::Number(n)]) => {
+                n.checked_neg().map_or(Value::Invalid, Value::Number)
+            },
+            (SyntaxNodeKind::Expr(Expr::Binary), [Value::Number(lhs), Value::Symbol(op), Value::Number(rhs)]) => {
+                let result = match op {
+                    Symbol::Plus => lhs.checked_add(*rhs),
+                    Symbol::Minus => lhs.checked_sub(*rhs),
+                    Symbol::Asterisk => lhs.checked_mul(*rhs),
+                    Symbol::ForwardSlash => lhs.checked_div(*rhs),
+                    _ => None,
+                };
+                result.map_or(Value::Invalid, Value::Number)
+            },
+            _ => Value::Invalid,
+        },
+    });
+
+    match value {
+        Value::Number(n) => Some(n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SyntaxBuilder;
+    use crate::make;
+
+    #[test]
+    fn test_eval() {
+        // a * (-2 + a) - 10
+        let mut b = SyntaxBuilder::new(Arena::new());
+
+        let root = make!(b => binary {
+            make!(b => binary {
+                make!(b => token { "a", 0 }),
+                make!(b => token { "*", 2 }),
+                make!(b => group {
+                    make!(b => binary {
+                        make!(b => unary {
+                            make!(b => token { "-", 5 }),
+                            make!(b => token { "2", 6 }),
+                        }),
+                        make!(b => token { "+", 8 }),
+                        make!(b => token { "a", 10 }),
+                    }),
+                }),
+            }),
+            make!(b => token { "-", 13 }),
+            make!(b => token { "10", 15 }),
+        });
+
+        let vars: HashMap<&str, i64> = vec![("a", 3)].into_iter().collect();
+        assert_eq!(eval(b.arena(), root, &vars), Some(-7));
+
+        let vars: HashMap<&str, i64> = vec![("a", -4)].into_iter().collect();
+        assert_eq!(eval(b.arena(), root, &vars), Some(14));
+
+        // Unknown identifiers cannot be evaluated.
+        assert_eq!(eval(b.arena(), root, &HashMap::new()), None);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::builder::{kind, Syntax};
+use isaac::{Arena, NodeId, Selector};
+
+/// A problem found in an expression, pointing at the offending node.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Lint {
+    pub id: NodeId,
+    pub message: &'static str,
+}
+
+const RULES: &[(&str, &str)] = &[
+    ("Group > Token", "unnecessary parentheses around a single token"),
+    ("Group > Group", "doubled parentheses"),
+    ("Unary > Unary", "double negation"),
+];
+
+/// Runs every lint rule over the expression rooted at `root`.
+pub fn lint(arena: &Arena<Syntax>, root: NodeId) -> Vec<Lint> {
+    let mut lints = Vec::new();
+
+    for &(selector, message) in RULES {
+        let selector = Selector::parse(selector, kind).expect("lint selectors should be valid");
+        lints.extend(arena.select(root, &selector).map(|id| Lint { id, message }));
+    }
+
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SyntaxBuilder;
+    use crate::make;
+
+    #[test]
+    fn test_lint() {
+        // ((a)) + --1
+        let mut b = SyntaxBuilder::new(Arena::new());
+
+        let a = make!(b => token { "a", 2 });
+        let inner = make!(b => group { a });
+        let outer = make!(b => group { inner });
+        let one = make!(b => token { "1", 10 });
+        let negated = make!(b => unary {
+            make!(b => token { "-", 9 }),
+            one,
+        });
+        let root = make!(b => binary {
+            outer,
+            make!(b => token { "+", 6 }),
+            make!(b => unary {
+                make!(b => token { "-", 8 }),
+                negated,
+            }),
+        });
+
+        assert_eq!(lint(b.arena(), root), vec![
+            Lint { id: a, message: "unnecessary parentheses around a single token" },
+            Lint { id: inner, message: "doubled parentheses" },
+            Lint { id: negated, message: "double negation" },
+        ]);
+    }
+}
@@ -1,10 +1,14 @@
 mod builder;
 mod cache;
+mod eval;
+mod lint;
 mod node;
+mod simplify;
 mod token;
 
 use builder::SyntaxBuilder;
 use isaac::Arena;
+use std::collections::HashMap;
 
 fn main() {
     // a * (-2 + a) - 10
@@ -20,7 +24,7 @@ fn main() {
     //       2
     let mut b = SyntaxBuilder::new(Arena::new());
 
-    make!(b => binary {
+    let root = make!(b => binary {
         make!(b => binary {
             make!(b => token { "a", 0 }),
             make!(b => token { "*", 2 }),
@@ -40,4 +44,14 @@ fn main() {
     });
 
     println!("{:#?}", b.nodes());
+
+    let vars: HashMap<&str, i64> = vec![("a", 3)].into_iter().collect();
+    println!("{} = {:?} where a = 3", b.to_sexpr(root), eval::eval(b.arena(), root, &vars));
+
+    for lint in lint::lint(b.arena(), root) {
+        println!("warning: {} at {:?}", lint.message, lint.id);
+    }
+
+    let root = simplify::simplify(b.arena_mut(), root);
+    println!("simplified: {}", b.to_sexpr(root));
 }
@@ -7,6 +7,10 @@ impl SyntaxNode {
     pub fn new(kind: SyntaxNodeKind) -> Self {
         Self { kind }
     }
+
+    pub fn kind(&self) -> SyntaxNodeKind {
+        self.kind
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -0,0 +1,75 @@
+use crate::builder::{kind, Syntax};
+use isaac::{Arena, NodeId, Pattern, Rewriter};
+
+/// Returns `true` if the node at `id` is the given symbol token.
+fn is_symbol(arena: &Arena<Syntax>, id: Option<NodeId>, text: &str) -> bool {
+    match id.and_then(|id| arena.node_at(id)).map(|node| node.data()) {
+        Some(Syntax::Token(token)) => token.text() == text,
+        _ => false,
+    }
+}
+
+/// Removes redundant parentheses and double negations from the expression
+/// rooted at `root`, returning the root of the simplified expression.
+pub fn simplify(arena: &mut Arena<Syntax>, root: NodeId) -> NodeId {
+    let pattern = |text| Pattern::parse(text, kind).expect("simplify patterns should be valid");
+
+    let mut rewriter = Rewriter::new()
+        // (a) => a
+        .rule(pattern("Group($x:Token)"), |_, m| m.capture("x"))
+        // ((a + b)) => (a + b)
+        .rule(pattern("Group($x:Group)"), |_, m| m.capture("x"))
+        // --a => a
+        .rule(pattern("Unary($outer:Token, Unary($inner:Token, $x))"), |arena, m| {
+            if is_symbol(arena, m.capture("outer"), "-") && is_symbol(arena, m.capture("inner"), "-") {
+                m.capture("x")
+            } else {
+                None
+            }
+        });
+
+    rewriter.rewrite(arena, root).expect("simplify rules should reach a fixpoint").root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::SyntaxBuilder;
+    use crate::make;
+
+    #[test]
+    fn test_simplify() {
+        // ((a)) * --((b + 1))
+        let mut b = SyntaxBuilder::new(Arena::new());
+
+        let root = make!(b => binary {
+            make!(b => group {
+                make!(b => group {
+                    make!(b => token { "a", 2 }),
+                }),
+            }),
+            make!(b => token { "*", 6 }),
+            make!(b => unary {
+                make!(b => token { "-", 8 }),
+                make!(b => unary {
+                    make!(b => token { "-", 9 }),
+                    make!(b => group {
+                        make!(b => group {
+                            make!(b => binary {
+                                make!(b => token { "b", 12 }),
+                                make!(b => token { "+", 14 }),
+                                make!(b => token { "1", 16 }),
+                            }),
+                        }),
+                    }),
+                }),
+            }),
+        });
+
+        let root = simplify(b.arena_mut(), root);
+        assert_eq!(
+            b.to_sexpr(root),
+            "(binary (token a) (token *) (group (binary (token b) (token +) (token 1))))",
+        );
+    }
+}
@@ -11,6 +11,14 @@ impl SyntaxToken {
     pub fn new(raw: Rc<RawSyntaxTokenData>, start: usize, len: usize) -> Self {
         Self { raw, start, len }
     }
+
+    pub fn kind(&self) -> SyntaxTokenKind {
+        self.raw.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.raw.text
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
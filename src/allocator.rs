@@ -0,0 +1,123 @@
+use crate::Arena;
+use allocator_api2::alloc::Allocator;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Creates a new empty `Arena` allocating from `alloc`.
+    ///
+    /// Both the storage of the nodes and the children of every node are
+    /// allocated with `alloc`, such as a bump or region allocator which frees
+    /// everything at once. Allocators come from the `allocator-api2` crate,
+    /// which mirrors the unstable `Allocator` trait of the standard library on
+    /// stable Rust. Arenas created with `Arena::map`, `Arena::snapshot` and
+    /// the like allocate from the same allocator, while parsing, diffing and
+    /// rewriting only work on arenas using the global allocator.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// # use allocator_api2::alloc::Global;
+    /// let arena = &mut Arena::new_in(Global);
+    /// let root = arena.insert("+");
+    /// let one = arena.insert("1");
+    /// let two = arena.insert("2");
+    ///
+    /// root
+    ///     .add_child(arena, one)
+    ///     .add_child(arena, two);
+    ///
+    /// assert_eq!(root.children(arena), &[one, two]);
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+    /// ```
+    pub fn new_in(alloc: A) -> Self {
+        Self::empty_in(alloc)
+    }
+
+    /// Returns a reference to the allocator of the `Arena`.
+    pub fn allocator(&self) -> &A {
+        self.nodes.allocator()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::CHUNK_SIZE;
+    use allocator_api2::alloc::{AllocError, Global, Layout};
+    use core::cell::Cell;
+    use core::ptr::NonNull;
+
+    #[derive(Default)]
+    struct Counts {
+        allocated: Cell<usize>,
+        freed: Cell<usize>,
+    }
+
+    /// Counts the allocations it forwards to the global allocator.
+    #[derive(Clone, Copy)]
+    struct Counting<'a>(&'a Counts);
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.allocated.set(self.0.allocated.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.freed.set(self.0.freed.get() + 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_arena_new_in() {
+        let counts = Counts::default();
+
+        {
+            let arena = &mut Arena::new_in(Counting(&counts));
+            let root = arena.insert(1);
+            let two = arena.insert(2);
+            let three = arena.insert(3);
+            let four = arena.insert(4);
+
+            // The first chunk of nodes.
+            assert_eq!(counts.allocated.get(), 1);
+
+            // The children of the root and of three, which have room for a
+            // few children before growing.
+            root.add_child(arena, three);
+            root.insert_child(arena, 0, two);
+            three.add_child(arena, four);
+            assert_eq!(counts.allocated.get(), 3);
+
+            assert_eq!(arena.root(), Some(root));
+            assert_eq!(root.children(arena), &[two, three]);
+            assert_eq!(arena.lca(two, four), Some(root));
+            assert_eq!(arena.to_sexpr(root), "(1 (2) (3 (4)))");
+
+            // Filling the first chunk allocates the second one.
+            for i in 5..=CHUNK_SIZE as i32 + 1 {
+                arena.insert(i);
+            }
+            assert_eq!(counts.allocated.get(), 4);
+
+            // Writing to a shared chunk copies it along with the children of
+            // its nodes.
+            let snapshot = arena.snapshot();
+            four.set_data(arena, 5);
+            assert_eq!(counts.allocated.get(), 7);
+            assert_eq!(snapshot.arena().to_sexpr(root), "(1 (2) (3 (4)))");
+            drop(snapshot);
+
+            let doubled = arena.map(|_, data| data * 2);
+            assert_eq!(doubled.to_sexpr(root), "(2 (4) (6 (10)))");
+            assert_eq!(counts.allocated.get(), 11);
+            assert_eq!(counts.freed.get(), 3);
+        }
+
+        assert_eq!(counts.freed.get(), counts.allocated.get());
+    }
+}
@@ -0,0 +1,289 @@
+#[cfg(not(target_has_atomic = "ptr"))]
+use alloc::rc::Rc as Shared;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc as Shared;
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec as AllocVec;
+use core::fmt;
+use core::iter::FromIterator;
+use core::ops::{Index, IndexMut};
+
+/// The number of values stored in each chunk.
+pub(crate) const CHUNK_SIZE: usize = 64;
+
+/// A vector split into fixed-size chunks, which can be shared between copies
+/// in constant time and are only copied when written to.
+///
+/// Both the list of chunks and every chunk are reference counted. Sharing a
+/// `Chunks` clones the outer reference, and the first write to a shared copy
+/// copies the list of chunks and the chunk being written to. Values are
+/// copied with a function captured when the storage is first shared, so
+/// that `T: Clone` is only required at that point.
+///
+/// The values themselves are stored in vectors allocated with `A`, while
+/// the list of chunks and the counts use the global allocator. The counts
+/// are kept in an `Arc`, or in an `Rc` on targets without atomic pointers,
+/// such as `thumbv6m-none-eabi`. An `Arena` is therefore neither `Send` nor
+/// `Sync` on those targets.
+pub(crate) struct Chunks<T, A: Allocator = Global> {
+    chunks: Shared<Vec<Shared<AllocVec<T, A>>>>,
+    len: usize,
+    clone: Option<fn(&T) -> T>,
+    alloc: A,
+}
+
+impl<T> Chunks<T> {
+    pub(crate) fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<T, A> Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new_in(alloc: A) -> Self {
+        Self { chunks: Shared::new(Vec::new()), len: 0, clone: None, alloc }
+    }
+
+    pub(crate) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            Some(&self.chunks[index / CHUNK_SIZE][index % CHUNK_SIZE])
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &T> {
+        self.chunks.iter().flat_map(|chunk| chunk.iter())
+    }
+
+    pub(crate) fn push(&mut self, value: T) {
+        if self.len.is_multiple_of(CHUNK_SIZE) {
+            let chunk = AllocVec::with_capacity_in(CHUNK_SIZE, self.alloc.clone());
+            self.chunks_mut().push(Shared::new(chunk));
+        }
+
+        let last = self.len / CHUNK_SIZE;
+        self.chunk_mut(last).push(value);
+        self.len += 1;
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let last = self.len.checked_sub(1)? / CHUNK_SIZE;
+        let value = self.chunk_mut(last).pop();
+        self.len -= 1;
+
+        if self.len.is_multiple_of(CHUNK_SIZE) {
+            self.chunks_mut().pop();
+        }
+
+        value
+    }
+
+    /// Returns a copy sharing every chunk with this one.
+    pub(crate) fn share(&mut self, clone: fn(&T) -> T) -> Self {
+        self.clone = Some(clone);
+        Self { chunks: Shared::clone(&self.chunks), len: self.len, clone: self.clone, alloc: self.alloc.clone() }
+    }
+
+    /// Returns every chunk as a slice. Every chunk but the last one holds
+    /// `CHUNK_SIZE` values.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn slices(&self) -> Vec<&[T]> {
+        self.chunks.iter().map(|chunk| chunk.as_slice()).collect()
+    }
+
+    /// Returns every chunk as a mutable slice, copying the ones still shared.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn slices_mut(&mut self) -> Vec<&mut [T]> {
+        let clone = self.clone;
+        self.chunks_mut().iter_mut()
+            .map(|chunk| unshare(chunk, clone).as_mut_slice())
+            .collect()
+    }
+
+    fn chunks_mut(&mut self) -> &mut Vec<Shared<AllocVec<T, A>>> {
+        if Shared::get_mut(&mut self.chunks).is_none() {
+            self.chunks = Shared::new(self.chunks.as_ref().clone());
+        }
+
+        Shared::get_mut(&mut self.chunks).expect("chunk list should be unique")
+    }
+
+    fn chunk_mut(&mut self, index: usize) -> &mut AllocVec<T, A> {
+        let clone = self.clone;
+        unshare(&mut self.chunks_mut()[index], clone)
+    }
+}
+
+/// Copies `chunk` if it is shared, returning a mutable reference to it.
+fn unshare<T, A>(chunk: &mut Shared<AllocVec<T, A>>, clone: Option<fn(&T) -> T>) -> &mut AllocVec<T, A>
+where
+    A: Allocator + Clone,
+{
+    if Shared::get_mut(chunk).is_none() {
+        let clone = clone.expect("shared chunks should have a clone function");
+        let mut copy = AllocVec::with_capacity_in(CHUNK_SIZE, chunk.allocator().clone());
+        copy.extend(chunk.iter().map(clone));
+        *chunk = Shared::new(copy);
+    }
+
+    Shared::get_mut(chunk).expect("chunk should be unique")
+}
+
+// Copies can only be written to if the chunks were shared with
+// `Chunks::share` before, which captures the function copying values.
+impl<T, A> Clone for Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self { chunks: Shared::clone(&self.chunks), len: self.len, clone: self.clone, alloc: self.alloc.clone() }
+    }
+}
+
+impl<T, A> Index<usize> for Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index should be in bounds")
+    }
+}
+
+impl<T, A> IndexMut<usize> for Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        assert!(index < self.len, "index should be in bounds");
+        &mut self.chunk_mut(index / CHUNK_SIZE)[index % CHUNK_SIZE]
+    }
+}
+
+impl<T, A> Extend<T> for Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Chunks<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut chunks = Self::new();
+        chunks.extend(iter);
+        chunks
+    }
+}
+
+impl<T> From<Vec<T>> for Chunks<T> {
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T, A> IntoIterator for Chunks<T, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let clone = self.clone;
+        let chunks = Shared::try_unwrap(self.chunks).unwrap_or_else(|chunks| chunks.as_ref().clone());
+        let mut values = Vec::with_capacity(self.len);
+
+        for chunk in chunks {
+            match Shared::try_unwrap(chunk) {
+                Ok(chunk) => values.extend(chunk),
+                Err(chunk) => {
+                    let clone = clone.expect("shared chunks should have a clone function");
+                    values.extend(chunk.iter().map(clone));
+                },
+            }
+        }
+
+        values.into_iter()
+    }
+}
+
+impl<T> Default for Chunks<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, A> fmt::Debug for Chunks<T, A>
+where
+    T: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, A> PartialEq for Chunks<T, A>
+where
+    T: PartialEq,
+    A: Allocator + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks() {
+        let mut chunks: Chunks<usize> = (0..200).collect();
+        let shared = chunks.share(usize::clone);
+        assert!(Shared::ptr_eq(&chunks.chunks, &shared.chunks));
+
+        chunks[10] = 1000;
+        chunks.push(200);
+        assert_eq!(chunks.pop(), Some(200));
+        for _ in 0..72 {
+            chunks.pop();
+        }
+
+        assert_eq!(chunks.len(), 128);
+        assert_eq!(chunks[10], 1000);
+        assert_eq!(shared.len(), 200);
+        assert_eq!(shared[10], 10);
+        assert!(shared.iter().copied().eq(0..200));
+
+        // Only the chunks that were written to have been copied.
+        assert!(!Shared::ptr_eq(&chunks.chunks[0], &shared.chunks[0]));
+        assert!(Shared::ptr_eq(&chunks.chunks[1], &shared.chunks[1]));
+
+        let values: Vec<usize> = shared.into_iter().collect();
+        assert_eq!(values, (0..200).collect::<Vec<_>>());
+        assert_eq!(Shared::strong_count(&chunks.chunks[1]), 1);
+    }
+}
@@ -0,0 +1,36 @@
+// Maps and sets which fall back to `alloc` when the `std` feature is
+// disabled, and the hasher used for digests in every build. Without `std`,
+// maps and sets are B-trees, which need their keys to be `Ord` instead of
+// `Hash`. Every key used in this crate is both.
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{hash_map as map, HashMap as Map, HashSet as Set};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{btree_map as map, BTreeMap as Map, BTreeSet as Set};
+
+/// A 64-bit FNV-1a hasher, used to compute digests of subtrees.
+///
+/// It is much weaker than the SipHash used by `std`, but digests do not need
+/// to resist collisions, and using it with and without the `std` feature
+/// keeps them the same in both builds.
+pub(crate) struct FnvHasher(u64);
+
+impl FnvHasher {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl core::hash::Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{Arena, Node, NodeId};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// The number of slots in the first bucket, as a power of two. Every
+/// following bucket is twice as large as the previous one.
+const FIRST_BUCKET_BITS: u32 = 5;
+
+/// The number of buckets needed to hold `usize::MAX` nodes.
+const BUCKETS: usize = (usize::BITS - FIRST_BUCKET_BITS) as usize;
+
+/// A parent link which has not been set.
+const NO_PARENT: usize = usize::MAX;
+
+/// A parent link claimed by a parent whose insertion is still in progress.
+const CLAIMED: usize = usize::MAX - 1;
+
+/// An append-only arena which nodes can be inserted into from many threads
+/// at once.
+///
+/// Nodes are stored in buckets of doubling size, which are allocated the
+/// first time they are needed and never move, so inserting a node only takes
+/// a shared reference. Since children must already exist when their parent
+/// is inserted, trees are built bottom-up with
+/// `ConcurrentArena::insert_with_children`, which is how parsers usually
+/// produce them anyway.
+///
+/// Once every thread is done, `ConcurrentArena::into_arena` turns it into a
+/// regular `Arena`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::ConcurrentArena;
+/// # use std::thread;
+/// let arena = ConcurrentArena::new();
+///
+/// // Parse every function on its own thread.
+/// let functions: Vec<_> = thread::scope(|scope| {
+///     let handles: Vec<_> = ["f", "g", "h"].iter()
+///         .map(|&name| {
+///             let arena = &arena;
+///             scope.spawn(move || {
+///                 let body = arena.insert("body".to_string());
+///                 arena.insert_with_children(format!("fn {}", name), [body])
+///             })
+///         })
+///         .collect();
+///
+///     handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+/// });
+///
+/// let file = arena.insert_with_children("file".to_string(), functions);
+/// let mut arena = arena.into_arena();
+/// arena.set_root(file);
+///
+/// assert_eq!(arena.len(), 7);
+/// assert_eq!(arena.to_sexpr(file), "(file (fn f (body)) (fn g (body)) (fn h (body)))");
+/// ```
+pub struct ConcurrentArena<T> {
+    buckets: [OnceLock<Bucket<T>>; BUCKETS],
+    len: AtomicUsize,
+}
+
+type Bucket<T> = Box<[OnceLock<Slot<T>>]>;
+
+struct Slot<T> {
+    data: T,
+    parent: AtomicUsize,
+    children: Box<[NodeId]>,
+}
+
+impl<T> ConcurrentArena<T> {
+    /// Creates a new empty `ConcurrentArena`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes inserted into the arena so far, including
+    /// nodes whose insertion is still in progress on another thread.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if no nodes have been inserted into the arena yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts a new value into the arena and returns its `NodeId`.
+    pub fn insert(&self, data: T) -> NodeId {
+        self.insert_with_children(data, [])
+    }
+
+    /// Inserts a new value into the arena with the given children, and
+    /// returns its `NodeId`. The new node becomes the parent of every child.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a child has not been inserted yet, or already has a parent.
+    /// The arena is left unchanged in that case.
+    pub fn insert_with_children<I>(&self, data: T, children: I) -> NodeId
+    where
+        I: IntoIterator<Item = NodeId>,
+    {
+        let children: Box<[NodeId]> = children.into_iter().collect();
+
+        // Claim every child before the node is given an index, so that
+        // nothing is published if one of them cannot be adopted.
+        for (claimed, &child) in children.iter().enumerate() {
+            let error = match self.slot(child) {
+                Some(slot) => match slot.parent.compare_exchange(NO_PARENT, CLAIMED, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => continue,
+                    Err(_) => "child should not already have a parent",
+                },
+                None => "child should be inserted before its parent",
+            };
+
+            for &child in &children[..claimed] {
+                self.set_parent(child, NO_PARENT);
+            }
+            panic!("{}", error);
+        }
+
+        let index = self.len.fetch_add(1, Ordering::AcqRel);
+
+        let (bucket, offset) = locate(index).expect("arena should have room for another node");
+        let bucket = self.buckets[bucket].get_or_init(|| {
+            (0..bucket_size(bucket)).map(|_| OnceLock::new()).collect()
+        });
+
+        let slot = Slot { data, parent: AtomicUsize::new(NO_PARENT), children };
+        if bucket[offset].set(slot).is_err() {
+            unreachable!("slot should only be written once");
+        }
+
+        // Link the children once the parent can be read, so that the parent
+        // of a node can always be looked up.
+        let children = &bucket[offset].get().expect("slot should be written").children;
+        for &child in children.iter() {
+            self.set_parent(child, index);
+        }
+
+        NodeId(index)
+    }
+
+    /// Returns a reference to the data of the node at `id`, or `None` if it
+    /// has not been inserted yet.
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        Some(&self.slot(id)?.data)
+    }
+
+    /// Returns the parent of the node at `id`, if it has one yet.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        match self.slot(id)?.parent.load(Ordering::Acquire) {
+            NO_PARENT | CLAIMED => None,
+            parent => Some(NodeId(parent)),
+        }
+    }
+
+    /// Returns the children of the node at `id`, or `None` if it has not been
+    /// inserted yet.
+    pub fn children(&self, id: NodeId) -> Option<&[NodeId]> {
+        Some(&self.slot(id)?.children)
+    }
+
+    /// Consumes the `ConcurrentArena`, returning a regular `Arena` with the
+    /// same nodes and `NodeId`s.
+    ///
+    /// The order in which nodes were inserted depends on how the threads
+    /// raced, so the root of the arena is only set when a single node has no
+    /// parent. Otherwise it is left as `None`, to be set with
+    /// `Arena::set_root`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the insertion of a node was never completed, which can only
+    /// happen if a thread panicked while inserting it.
+    pub fn into_arena(self) -> Arena<T> {
+        let len = self.len.into_inner();
+        let mut slots = IntoIterator::into_iter(self.buckets)
+            .flat_map(|bucket| bucket.into_inner().into_iter().flat_map(|slots| slots.into_vec()));
+
+        let nodes: Vec<Node<T>> = (0..len)
+            .map(|index| {
+                let slot = slots.next()
+                    .and_then(OnceLock::into_inner)
+                    .expect("every node should be fully inserted");
+
+                Node {
+                    id: NodeId(index),
+                    data: slot.data,
+                    parent: match slot.parent.into_inner() {
+                        NO_PARENT => None,
+                        CLAIMED => panic!("every node should be fully inserted"),
+                        parent => Some(NodeId(parent)),
+                    },
+                    children: slot.children.iter().copied().collect(),
+                }
+            })
+            .collect();
+
+        let mut tops = nodes.iter().filter(|node| node.parent.is_none());
+        let root = match (tops.next(), tops.next()) {
+            (Some(top), None) => Some(top.id),
+            _ => None,
+        };
+
+        Arena { nodes: nodes.into_iter().collect(), root, ..Arena::new() }
+    }
+
+    /// Sets the parent link of a child claimed by `insert_with_children`.
+    fn set_parent(&self, child: NodeId, parent: usize) {
+        let slot = self.slot(child).expect("claimed child should be inserted");
+        slot.parent.store(parent, Ordering::Release);
+    }
+
+    fn slot(&self, id: NodeId) -> Option<&Slot<T>> {
+        let (bucket, offset) = locate(id.index())?;
+        self.buckets.get(bucket)?.get()?[offset].get()
+    }
+}
+
+impl<T> Default for ConcurrentArena<T> {
+    fn default() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| OnceLock::new()),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+/// Returns the bucket holding the node at `index`, and its offset within the
+/// bucket, or `None` if `index` is past the last bucket.
+fn locate(index: usize) -> Option<(usize, usize)> {
+    let shifted = index.checked_add(1 << FIRST_BUCKET_BITS)?;
+    let bits = usize::BITS - 1 - shifted.leading_zeros();
+    let bucket = (bits - FIRST_BUCKET_BITS) as usize;
+    Some((bucket, shifted - (1 << bits)))
+}
+
+fn bucket_size(bucket: usize) -> usize {
+    1 << (bucket as u32 + FIRST_BUCKET_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{panic, thread};
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), Some((0, 0)));
+        assert_eq!(locate(31), Some((0, 31)));
+        assert_eq!(locate(32), Some((1, 0)));
+        assert_eq!(locate(95), Some((1, 63)));
+        assert_eq!(locate(96), Some((2, 0)));
+        assert_eq!(locate(usize::MAX - 32), Some((BUCKETS - 1, bucket_size(BUCKETS - 1) - 1)));
+        assert_eq!(locate(usize::MAX), None);
+        assert_eq!(bucket_size(2), 128);
+    }
+
+    #[test]
+    fn test_concurrent_arena() {
+        let arena = ConcurrentArena::new();
+        assert!(arena.is_empty());
+        assert_eq!(arena.get(NodeId(0)), None);
+
+        let roots: Vec<NodeId> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|thread| {
+                    let arena = &arena;
+                    scope.spawn(move || {
+                        // Build a chain of 100 nodes per thread.
+                        let mut top = arena.insert((thread, 0));
+                        for depth in 1..100 {
+                            top = arena.insert_with_children((thread, depth), [top]);
+                        }
+                        top
+                    })
+                })
+                .collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        assert_eq!(arena.len(), 800);
+        for (thread, &root) in roots.iter().enumerate() {
+            assert_eq!(arena.get(root), Some(&(thread, 99)));
+            assert_eq!(arena.parent(root), None);
+
+            let child = arena.children(root).unwrap()[0];
+            assert_eq!(arena.parent(child), Some(root));
+            assert_eq!(arena.get(child), Some(&(thread, 98)));
+        }
+
+        let arena = arena.into_arena();
+        assert_eq!(arena.len(), 800);
+        assert_eq!(arena.root(), None);
+        for (thread, &root) in roots.iter().enumerate() {
+            assert_eq!(arena.height(root), 99);
+            assert!(arena.preorder(root).all(|(id, _)| arena.node_at(id).unwrap().data().0 == thread));
+        }
+    }
+
+    #[test]
+    fn test_invalid_children() {
+        let arena = ConcurrentArena::new();
+        let child = arena.insert(1);
+        assert_eq!(arena.get(NodeId::from(usize::MAX)), None);
+        assert_eq!(arena.parent(NodeId::from(usize::MAX)), None);
+        assert_eq!(arena.children(NodeId::from(usize::MAX)), None);
+
+        // A missing or repeated child leaves the other children unclaimed.
+        let missing = panic::catch_unwind(|| arena.insert_with_children(2, [child, NodeId(5)]));
+        assert!(missing.is_err());
+        let repeated = panic::catch_unwind(|| arena.insert_with_children(2, [child, child]));
+        assert!(repeated.is_err());
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.parent(child), None);
+
+        let parent = arena.insert_with_children(2, [child]);
+        assert_eq!(arena.parent(child), Some(parent));
+
+        // The only node without a parent becomes the root, even though it
+        // was not inserted first.
+        let converted = arena.into_arena();
+        assert_eq!(converted.root(), Some(parent));
+        assert_eq!(converted.to_sexpr(parent), "(2 (1))");
+
+        // A child left claimed by an interrupted insertion is rejected.
+        let arena = ConcurrentArena::new();
+        let child = arena.insert(1);
+        arena.set_parent(child, CLAIMED);
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| arena.into_arena())).is_err());
+    }
+}
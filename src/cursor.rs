@@ -0,0 +1,316 @@
+use crate::{Arena, Node, NodeId};
+use allocator_api2::alloc::{Allocator, Global};
+
+/// A cursor pointing at a node of an `Arena`, which can move around the tree
+/// and make local edits to it.
+///
+/// Movement methods return `false` and leave the cursor where it is if there
+/// is no node to move to. Edits keep parent and child links consistent, as
+/// well as any caches enabled on the arena, and each edit is recorded as a
+/// single undo step when the journal is enabled.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena};
+/// // a * b + c
+/// let arena = &mut Arena::new();
+/// let root = tree!(arena, "+" => ["*" => ["a", "b"], "c"]);
+///
+/// // (a * b) + c
+/// let mut cursor = arena.cursor(root);
+/// assert!(cursor.goto_first_child());
+/// cursor.wrap_in("()");
+///
+/// // (a * b) + d
+/// assert!(cursor.goto_parent());
+/// assert!(cursor.goto_next_sibling());
+/// assert_eq!(cursor.replace("d"), "c");
+///
+/// assert_eq!(arena.to_sexpr(root), r#"(+ ("()" (* (a) (b))) (d))"#);
+/// ```
+pub struct Cursor<'a, T, A: Allocator = Global> {
+    arena: &'a mut Arena<T, A>,
+    id: NodeId,
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns a `Cursor` pointing at the node at `id`.
+    pub fn cursor(&mut self, id: NodeId) -> Cursor<'_, T, A> {
+        Cursor { arena: self, id }
+    }
+}
+
+impl<'a, T, A> Cursor<'a, T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the `NodeId` of the node the cursor points at.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Returns the node the cursor points at.
+    pub fn node(&self) -> &Node<T, A> {
+        self.arena.get(self.id)
+    }
+
+    /// Returns the data of the node the cursor points at.
+    pub fn data(&self) -> &T {
+        self.node().data()
+    }
+
+    /// Returns a reference to the underlying `Arena`.
+    pub fn arena(&self) -> &Arena<T, A> {
+        self.arena
+    }
+
+    /// Moves the cursor to the parent of the current node.
+    pub fn goto_parent(&mut self) -> bool {
+        self.goto(self.node().parent())
+    }
+
+    /// Moves the cursor to the first child of the current node.
+    pub fn goto_first_child(&mut self) -> bool {
+        self.goto(self.node().children().first().copied())
+    }
+
+    /// Moves the cursor to the last child of the current node.
+    pub fn goto_last_child(&mut self) -> bool {
+        self.goto(self.node().children().last().copied())
+    }
+
+    /// Moves the cursor to the sibling following the current node.
+    pub fn goto_next_sibling(&mut self) -> bool {
+        let next = self.position()
+            .and_then(|(parent, index)| self.arena.get(parent).children().get(index + 1).copied());
+        self.goto(next)
+    }
+
+    /// Moves the cursor to the sibling preceding the current node.
+    pub fn goto_prev_sibling(&mut self) -> bool {
+        let prev = self.position()
+            .and_then(|(parent, index)| index.checked_sub(1).map(|index| self.arena.get(parent).children()[index]));
+        self.goto(prev)
+    }
+
+    /// Replaces the data of the current node, returning the previous data.
+    pub fn replace(&mut self, data: T) -> T {
+        self.arena.replace_data(self.id, data)
+    }
+
+    /// Inserts a new node right after the current node, among the children
+    /// of its parent, and returns its `NodeId`.
+    ///
+    /// The cursor stays on the current node. Returns `None` without inserting
+    /// anything if the current node has no parent.
+    pub fn insert_after(&mut self, data: T) -> Option<NodeId> {
+        let (parent, index) = self.position()?;
+        let id = self.arena.grouped(|arena| {
+            let id = arena.insert(data);
+            arena.attach(parent, index + 1, id);
+            id
+        });
+        Some(id)
+    }
+
+    /// Detaches the current node and its descendants from the tree.
+    ///
+    /// The cursor moves to the next sibling of the deleted node if there is
+    /// one, to its previous sibling otherwise, and to its parent if it was an
+    /// only child. The deleted nodes remain in the arena, so their `NodeId`s
+    /// stay valid.
+    ///
+    /// Returns `false` without deleting anything if the current node has no
+    /// parent.
+    pub fn delete(&mut self) -> bool {
+        let (parent, index) = match self.position() {
+            Some(position) => position,
+            None => return false,
+        };
+
+        let id = self.id;
+        self.arena.grouped(|arena| arena.detach(id));
+
+        let children = self.arena.get(parent).children();
+        self.id = children.get(index)
+            .or_else(|| index.checked_sub(1).and_then(|index| children.get(index)))
+            .copied()
+            .unwrap_or(parent);
+
+        true
+    }
+
+    /// Inserts a new node in the place of the current node, and makes the
+    /// current node its only child. Returns the `NodeId` of the new node.
+    ///
+    /// The cursor stays on the current node. If the current node was the root
+    /// of the arena, the new node becomes the root instead.
+    pub fn wrap_in(&mut self, data: T) -> NodeId {
+        let (id, position) = (self.id, self.position());
+
+        self.arena.grouped(|arena| {
+            let wrapper = arena.insert(data);
+
+            if let Some((parent, index)) = position {
+                arena.detach(id);
+                arena.attach(parent, index, wrapper);
+            }
+
+            arena.attach(wrapper, 0, id);
+
+            if arena.root() == Some(id) {
+                arena.set_root(wrapper);
+            }
+
+            wrapper
+        })
+    }
+
+    /// Returns the parent of the current node and the position of the node
+    /// among its children, or `None` if the node has no parent or was linked
+    /// to it with `NodeId::set_parent` alone.
+    fn position(&self) -> Option<(NodeId, usize)> {
+        let parent = self.node().parent()?;
+        let index = self.arena.get(parent).children().iter().position(|&child| child == self.id)?;
+        Some((parent, index))
+    }
+
+    fn goto(&mut self, id: Option<NodeId>) -> bool {
+        match id {
+            Some(id) => {
+                self.id = id;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor() {
+        //     1
+        //   / | \
+        //  2  3  4
+        //     |
+        //     5
+        let arena = &mut Arena::new();
+        let root = tree!(arena, 1 => [2, 3 => [5], 4]);
+        arena.enable_digests();
+        arena.enable_subtree_sizes();
+
+        let mut cursor = arena.cursor(root);
+        assert!(!cursor.goto_parent());
+        assert!(!cursor.goto_next_sibling());
+        assert!(!cursor.goto_prev_sibling());
+        assert!(!cursor.delete());
+        assert_eq!(cursor.insert_after(0), None);
+
+        assert!(cursor.goto_last_child());
+        assert_eq!(*cursor.data(), 4);
+        assert!(!cursor.goto_next_sibling());
+        assert!(cursor.goto_prev_sibling());
+        assert!(cursor.goto_first_child());
+        assert_eq!(*cursor.data(), 5);
+        assert!(!cursor.goto_first_child());
+        assert!(cursor.goto_parent());
+        assert!(cursor.goto_prev_sibling());
+        assert!(!cursor.goto_prev_sibling());
+        assert_eq!(*cursor.data(), 2);
+
+        let six = cursor.insert_after(6).unwrap();
+        assert_eq!(cursor.replace(7), 2);
+        assert!(cursor.goto_next_sibling());
+        assert_eq!(cursor.id(), six);
+
+        let wrapper = cursor.wrap_in(8);
+        assert_eq!(cursor.node().parent(), Some(wrapper));
+        assert_eq!(cursor.arena().to_sexpr(root), "(1 (7) (8 (6)) (3 (5)) (4))");
+
+        // Deleting moves to the next sibling, then the previous one, then the
+        // parent.
+        assert!(cursor.goto_parent());
+        assert!(cursor.delete());
+        assert_eq!(*cursor.data(), 3);
+        assert!(cursor.goto_next_sibling());
+        assert!(cursor.delete());
+        assert_eq!(*cursor.data(), 3);
+        assert!(cursor.goto_first_child());
+        assert!(cursor.delete());
+        assert_eq!(*cursor.data(), 3);
+        assert_eq!(cursor.arena().to_sexpr(root), "(1 (7) (3))");
+        assert_eq!(wrapper.parent(arena), None);
+
+        // Wrapping the root of the arena makes the wrapper its root.
+        let wrapper = arena.cursor(root).wrap_in(9);
+        assert_eq!(arena.root(), Some(wrapper));
+        assert_eq!(arena.to_sexpr(wrapper), "(9 (1 (7) (3)))");
+
+        assert_eq!(arena.subtree_size(wrapper), 4);
+        assert_eq!(arena.subtree_digest(wrapper), arena.subtree_hash(wrapper));
+    }
+
+    #[test]
+    fn test_cursor_half_linked() {
+        // A node whose parent does not list it among its children has no
+        // siblings to move to, and cannot be deleted or inserted after.
+        let arena = &mut Arena::new();
+        let root = tree!(arena, 1 => [2]);
+        let orphan = arena.insert(3);
+        orphan.set_parent(arena, root);
+
+        let mut cursor = arena.cursor(orphan);
+        assert!(!cursor.goto_next_sibling());
+        assert!(!cursor.goto_prev_sibling());
+        assert!(!cursor.delete());
+        assert_eq!(cursor.insert_after(4), None);
+        assert!(cursor.goto_parent());
+        assert_eq!(cursor.id(), root);
+        assert_eq!(arena.to_sexpr(root), "(1 (2))");
+    }
+
+    #[test]
+    fn test_cursor_undo() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "+" => ["1", "2"]);
+        let two = root.children(arena)[1];
+        arena.enable_journal();
+
+        // Every edit is undone in one step.
+        arena.cursor(two).wrap_in("()");
+        assert_eq!(arena.to_sexpr(root), r#"(+ (1) ("()" (2)))"#);
+        assert!(arena.undo());
+        assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+        assert_eq!(arena.len(), 3);
+
+        arena.cursor(root).wrap_in("-");
+        assert!(arena.undo());
+        assert_eq!(arena.root(), Some(root));
+
+        arena.cursor(two).insert_after("3");
+        assert!(arena.undo());
+        assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+
+        arena.cursor(two).delete();
+        assert!(arena.undo());
+        assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+        assert!(!arena.undo());
+
+        // Inside a transaction, edits become part of it.
+        arena.begin_transaction();
+        arena.cursor(two).wrap_in("()");
+        arena.cursor(root).insert_after("x");
+        arena.cursor(two).insert_after("3");
+        arena.commit();
+        assert!(arena.undo());
+        assert!(!arena.undo());
+        assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+    }
+}
@@ -0,0 +1,558 @@
+use crate::collections::{Map, Set};
+use crate::subtree::combine;
+use crate::{Arena, NodeId};
+use alloc::vec;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::hash::Hash;
+
+/// A single operation of an edit script produced by `diff`.
+///
+/// Every `NodeId` in an edit refers to a node in the arena being patched.
+/// Nodes created by an `Edit::Insert` are given the next free index of that
+/// arena, so later edits in the same script can refer to them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Edit<T> {
+    /// Inserts a new node holding `data`, which is given the index `id`, as
+    /// the child of `parent` at position `index`.
+    Insert { id: NodeId, parent: NodeId, index: usize, data: T },
+    /// Detaches the node `id` from its parent.
+    Delete { id: NodeId },
+    /// Replaces the data of the node `id`.
+    Update { id: NodeId, data: T },
+    /// Detaches the node `id` from its parent, before inserting it as the
+    /// child of `parent` at position `index`.
+    Move { id: NodeId, parent: NodeId, index: usize },
+}
+
+/// Computes an edit script that transforms the subtree at `old_root` in `old`
+/// into the subtree at `new_root` in `new`.
+///
+/// Nodes of the two trees are first matched up, in the spirit of GumTree:
+/// identical subtrees are matched greedily from the largest down, after which
+/// the remaining children of matched nodes are paired by equal data and then
+/// by position. The two roots are always matched with each other. The edit
+/// script is then generated from the matching following Chawathe et al.,
+/// moving matched nodes whose parents differ, inserting unmatched nodes of the
+/// new tree and deleting unmatched nodes of the old tree.
+///
+/// Applying the result to `old` with `patch` makes the subtree at `old_root`
+/// compare equal to the new tree with `Arena::subtree_eq`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{diff, patch, Arena, Edit, NodeId};
+/// let parse = |text| Arena::from_sexpr(text, |label| Ok::<_, ()>(label.to_string()));
+///
+/// let mut old = parse("(+ (1) (2))").unwrap();
+/// let new = parse("(+ (1) (3))").unwrap();
+/// let old_root = old.root().unwrap();
+/// let new_root = new.root().unwrap();
+///
+/// let edits = diff(&old, old_root, &new, new_root);
+/// assert_eq!(edits, vec![
+///     Edit::Update { id: NodeId::from(2), data: "3".to_string() },
+/// ]);
+///
+/// patch(&mut old, edits);
+/// assert!(old.subtree_eq(old_root, &new, new_root));
+/// ```
+pub fn diff<T>(old: &Arena<T>, old_root: NodeId, new: &Arena<T>, new_root: NodeId) -> Vec<Edit<T>>
+where
+    T: Clone + Hash + PartialEq,
+{
+    let old_summary = Summary::new(old, old_root);
+    let new_summary = Summary::new(new, new_root);
+
+    let mut matching = Matching::default();
+    matching.link(old_root, new_root);
+    matching.match_identical(old, &old_summary, new, &new_summary);
+    matching.match_children(old, &old_summary, new, &new_summary);
+
+    Script::new(old, new, &matching).generate(&old_summary, &new_summary)
+}
+
+/// Applies an edit script produced by `diff` to an arena, as a single undo
+/// step when its journal is enabled.
+///
+/// # Panics
+///
+/// Panics if the script refers to nodes that do not exist, or if an inserted
+/// node would not be given the index recorded in its `Edit::Insert`. Either
+/// happens when the script is applied to an arena other than the one it was
+/// computed from.
+pub fn patch<T, I>(arena: &mut Arena<T>, edits: I)
+where
+    I: IntoIterator<Item = Edit<T>>,
+{
+    arena.grouped(|arena| {
+        for edit in edits {
+            match edit {
+                Edit::Insert { id, parent, index, data } => {
+                    let inserted = arena.insert(data);
+                    assert_eq!(inserted, id, "edit script does not apply to this arena");
+                    parent.insert_child(arena, index, inserted);
+                },
+                Edit::Delete { id } => {
+                    id.detach(arena);
+                },
+                Edit::Update { id, data } => {
+                    id.set_data(arena, data);
+                },
+                Edit::Move { id, parent, index } => {
+                    id.detach(arena);
+                    parent.insert_child(arena, index, id);
+                },
+            }
+        }
+    });
+}
+
+/// The pre-order listing of a subtree, along with the hash and size of every
+/// subtree within it.
+struct Summary {
+    preorder: Vec<NodeId>,
+    hashes: Map<NodeId, u64>,
+    sizes: Map<NodeId, usize>,
+}
+
+impl Summary {
+    fn new<T>(arena: &Arena<T>, root: NodeId) -> Self
+    where
+        T: Hash,
+    {
+        let mut preorder = Vec::new();
+        let mut stack = vec![root];
+
+        while let Some(id) = stack.pop() {
+            preorder.push(id);
+            stack.extend(arena.get(id).children().iter().rev());
+        }
+
+        let mut hashes = Map::new();
+        let mut sizes = Map::new();
+
+        // Walking the pre-order backwards visits children before parents.
+        for &id in preorder.iter().rev() {
+            let node = arena.get(id);
+            let child_hashes: Vec<u64> = node.children().iter().map(|child| hashes[child]).collect();
+            let size = 1 + node.children().iter().map(|child| sizes[child]).sum::<usize>();
+
+            hashes.insert(id, combine(node.data(), &child_hashes));
+            sizes.insert(id, size);
+        }
+
+        Self { preorder, hashes, sizes }
+    }
+}
+
+/// A one-to-one mapping between nodes of the old and new trees.
+#[derive(Default)]
+struct Matching {
+    old_to_new: Map<NodeId, NodeId>,
+    new_to_old: Map<NodeId, NodeId>,
+}
+
+impl Matching {
+    fn link(&mut self, old: NodeId, new: NodeId) {
+        self.old_to_new.insert(old, new);
+        self.new_to_old.insert(new, old);
+    }
+
+    /// Links every node of two identical subtrees with each other.
+    fn link_subtrees<T>(&mut self, old: &Arena<T>, old_root: NodeId, new: &Arena<T>, new_root: NodeId) {
+        let mut stack = vec![(old_root, new_root)];
+
+        while let Some((a, b)) = stack.pop() {
+            self.link(a, b);
+            stack.extend(old.get(a).children().iter().copied().zip(new.get(b).children().iter().copied()));
+        }
+    }
+
+    /// Matches identical subtrees of at least two nodes, largest first.
+    fn match_identical<T>(&mut self, old: &Arena<T>, old_summary: &Summary, new: &Arena<T>, new_summary: &Summary)
+    where
+        T: PartialEq,
+    {
+        let mut buckets: Map<u64, Vec<NodeId>> = Map::new();
+        for &id in &old_summary.preorder {
+            if old_summary.sizes[&id] >= 2 {
+                buckets.entry(old_summary.hashes[&id]).or_default().push(id);
+            }
+        }
+
+        let mut candidates: Vec<NodeId> = new_summary.preorder.iter()
+            .copied()
+            .filter(|id| new_summary.sizes[id] >= 2)
+            .collect();
+        candidates.sort_by_key(|id| Reverse(new_summary.sizes[id]));
+
+        for b in candidates {
+            if self.new_to_old.contains_key(&b) {
+                continue;
+            }
+
+            let found = buckets.get(&new_summary.hashes[&b]).and_then(|bucket| {
+                bucket.iter().copied().find(|&a| {
+                    !self.old_to_new.contains_key(&a) && old.subtree_eq(a, new, b)
+                })
+            });
+
+            if let Some(a) = found {
+                self.link_subtrees(old, a, new, b);
+            }
+        }
+    }
+
+    /// Pairs up the unmatched children of matched nodes, from the top of the
+    /// new tree down.
+    ///
+    /// Children with identical subtrees are paired first, then children with
+    /// equal data, and finally any leftovers are paired by position as long
+    /// as they are both leaves or both have children.
+    fn match_children<T>(&mut self, old: &Arena<T>, old_summary: &Summary, new: &Arena<T>, new_summary: &Summary)
+    where
+        T: PartialEq,
+    {
+        for &b in &new_summary.preorder {
+            let a = match self.new_to_old.get(&b) {
+                Some(&a) => a,
+                None => continue,
+            };
+
+            let old_children = old.get(a).children();
+            let new_children = new.get(b).children();
+
+            for &new_child in new_children {
+                if self.new_to_old.contains_key(&new_child) {
+                    continue;
+                }
+
+                let found = old_children.iter().copied().find(|&old_child| {
+                    !self.old_to_new.contains_key(&old_child)
+                        && old_summary.hashes[&old_child] == new_summary.hashes[&new_child]
+                        && old.subtree_eq(old_child, new, new_child)
+                });
+
+                if let Some(old_child) = found {
+                    self.link_subtrees(old, old_child, new, new_child);
+                }
+            }
+
+            for &new_child in new_children {
+                if self.new_to_old.contains_key(&new_child) {
+                    continue;
+                }
+
+                let found = old_children.iter().copied().find(|&old_child| {
+                    !self.old_to_new.contains_key(&old_child)
+                        && old.get(old_child).data() == new.get(new_child).data()
+                });
+
+                if let Some(old_child) = found {
+                    self.link(old_child, new_child);
+                }
+            }
+
+            let old_left = old_children.iter().filter(|id| !self.old_to_new.contains_key(id));
+            let new_left = new_children.iter().filter(|id| !self.new_to_old.contains_key(id));
+            let pairs: Vec<(NodeId, NodeId)> = old_left.copied().zip(new_left.copied())
+                .filter(|&(old_child, new_child)| {
+                    old.get(old_child).children().is_empty() == new.get(new_child).children().is_empty()
+                })
+                .collect();
+
+            for (old_child, new_child) in pairs {
+                self.link(old_child, new_child);
+            }
+        }
+    }
+}
+
+/// Generates an edit script from a matching, keeping track of the shape the
+/// patched arena will have after each edit.
+struct Script<'a, T> {
+    old: &'a Arena<T>,
+    new: &'a Arena<T>,
+    parents: Vec<Option<NodeId>>,
+    children: Vec<Vec<NodeId>>,
+    partners: Map<NodeId, NodeId>,
+    reverse_partners: Map<NodeId, NodeId>,
+    in_order: Set<NodeId>,
+    edits: Vec<Edit<T>>,
+}
+
+impl<'a, T> Script<'a, T>
+where
+    T: Clone + PartialEq,
+{
+    fn new(old: &'a Arena<T>, new: &'a Arena<T>, matching: &Matching) -> Self {
+        let parents = old.nodes.iter().map(|node| node.parent()).collect();
+        let children = old.nodes.iter().map(|node| node.children().to_vec()).collect();
+
+        Self {
+            old,
+            new,
+            parents,
+            children,
+            partners: matching.new_to_old.clone(),
+            reverse_partners: matching.old_to_new.clone(),
+            in_order: Set::new(),
+            edits: Vec::new(),
+        }
+    }
+
+    fn generate(mut self, old_summary: &Summary, new_summary: &Summary) -> Vec<Edit<T>> {
+        let new_root = new_summary.preorder[0];
+        let mut queue = VecDeque::from(vec![new_root]);
+
+        while let Some(x) = queue.pop_front() {
+            match self.new.get(x).parent().filter(|_| x != new_root) {
+                Some(y) => self.place(x, self.partners[&y]),
+                None => self.update(x),
+            }
+
+            self.align_children(x);
+            queue.extend(self.new.get(x).children());
+        }
+
+        for &id in old_summary.preorder.iter().rev() {
+            if !self.reverse_partners.contains_key(&id) {
+                self.detach(id);
+                self.edits.push(Edit::Delete { id });
+            }
+        }
+
+        self.edits
+    }
+
+    /// Inserts or moves the partner of the new node `x` under `w`.
+    fn place(&mut self, x: NodeId, w: NodeId) {
+        let data = self.new.get(x).data();
+
+        let v = match self.partners.get(&x) {
+            Some(&v) => v,
+            None => {
+                let id = NodeId::from(self.parents.len());
+                let index = self.find_pos(x, w);
+
+                self.parents.push(None);
+                self.children.push(Vec::new());
+                self.attach(id, w, index);
+                self.link(id, x);
+                self.in_order.insert(x);
+                self.edits.push(Edit::Insert { id, parent: w, index, data: data.clone() });
+                return;
+            },
+        };
+
+        self.update(x);
+
+        if self.parents[v.index()] != Some(w) {
+            self.relocate(v, x, w);
+        }
+    }
+
+    /// Updates the partner of the new node `x` if their data differs.
+    fn update(&mut self, x: NodeId) {
+        let v = self.partners[&x];
+        let data = self.new.get(x).data();
+
+        if self.old.get(v).data() != data {
+            self.edits.push(Edit::Update { id: v, data: data.clone() });
+        }
+    }
+
+    /// Moves the children of the partner of `x` which are also partners of
+    /// children of `x` into the same relative order.
+    fn align_children(&mut self, x: NodeId) {
+        let w = self.partners[&x];
+
+        let s1: Vec<NodeId> = self.children[w.index()].iter()
+            .copied()
+            .filter(|a| {
+                self.reverse_partners.get(a)
+                    .is_some_and(|&b| self.new.get(b).parent() == Some(x))
+            })
+            .collect();
+        let s2: Vec<NodeId> = self.new.get(x).children().iter()
+            .copied()
+            .filter(|b| {
+                self.partners.get(b)
+                    .is_some_and(|&a| self.parents[a.index()] == Some(w))
+            })
+            .collect();
+
+        let partners: Vec<NodeId> = s2.iter().map(|b| self.partners[b]).collect();
+        let common = lcs(&s1, &partners);
+
+        for &a in &common {
+            self.in_order.insert(self.reverse_partners[&a]);
+        }
+
+        for b in s2 {
+            if !self.in_order.contains(&b) {
+                self.relocate(self.partners[&b], b, w);
+            }
+        }
+    }
+
+    /// Moves `v`, the partner of the new node `x`, under `w`.
+    fn relocate(&mut self, v: NodeId, x: NodeId, w: NodeId) {
+        self.detach(v);
+        let index = self.find_pos(x, w);
+        self.attach(v, w, index);
+        self.in_order.insert(x);
+        self.edits.push(Edit::Move { id: v, parent: w, index });
+    }
+
+    /// Finds the position under `w` that the partner of the new node `x`
+    /// should be placed at, which is just after the partner of its closest
+    /// left sibling that is already in order.
+    fn find_pos(&self, x: NodeId, w: NodeId) -> usize {
+        let siblings = match self.new.get(x).parent() {
+            Some(y) => self.new.get(y).children(),
+            None => return 0,
+        };
+
+        let position = siblings.iter()
+            .position(|&sibling| sibling == x)
+            .expect("node should be a child of its parent");
+        let left = siblings[..position].iter()
+            .rev()
+            .find(|sibling| self.in_order.contains(sibling));
+
+        match left {
+            Some(sibling) => {
+                let u = self.partners[sibling];
+                let index = self.children[w.index()].iter().position(|&child| child == u);
+                index.expect("in-order sibling should be placed under its parent") + 1
+            },
+            None => 0,
+        }
+    }
+
+    fn link(&mut self, patched: NodeId, new: NodeId) {
+        self.partners.insert(new, patched);
+        self.reverse_partners.insert(patched, new);
+    }
+
+    fn attach(&mut self, id: NodeId, parent: NodeId, index: usize) {
+        self.children[parent.index()].insert(index, id);
+        self.parents[id.index()] = Some(parent);
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        if let Some(parent) = self.parents[id.index()].take() {
+            let siblings = &mut self.children[parent.index()];
+            if let Some(index) = siblings.iter().position(|&child| child == id) {
+                siblings.remove(index);
+            }
+        }
+    }
+}
+
+/// Returns the longest common subsequence of two sequences of nodes.
+fn lcs(a: &[NodeId], b: &[NodeId]) -> Vec<NodeId> {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut common = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            common.push(a[i]);
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    common
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Arena<String> {
+        Arena::from_sexpr(text, |label| Ok::<_, ()>(label.to_string())).unwrap()
+    }
+
+    fn check(old: &str, new: &str) -> Vec<Edit<String>> {
+        let original = old;
+        let mut old = parse(old);
+        let new = parse(new);
+        let old_root = old.root().unwrap();
+        let new_root = new.root().unwrap();
+
+        let edits = diff(&old, old_root, &new, new_root);
+        old.enable_journal();
+        patch(&mut old, edits.clone());
+        assert_eq!(old.to_sexpr(old_root), new.to_sexpr(new_root));
+
+        // The whole script is undone in one step.
+        if !edits.is_empty() {
+            assert!(old.undo());
+            assert!(!old.undo());
+            assert_eq!(old.to_sexpr(old_root), original);
+        }
+
+        edits
+    }
+
+    #[test]
+    fn test_diff_patch() {
+        let expr = "(binary (token 1) (token +) (unary (token -) (token 1)))";
+
+        // Identical trees need no edits.
+        assert_eq!(check(expr, expr), vec![]);
+
+        // Swapping the operands of a binary expression moves them.
+        let edits = check(
+            "(binary (token a) (token *) (group (binary (token b) (token +) (token c))))",
+            "(binary (group (binary (token b) (token +) (token c))) (token *) (token a))",
+        );
+        assert!(edits.iter().all(|edit| matches!(edit, Edit::Move { .. })));
+
+        // Wrapping an operand in a group inserts one node and moves the other.
+        let edits = check(
+            expr,
+            "(binary (token 1) (token +) (group (unary (token -) (token 1))))",
+        );
+        assert_eq!(edits, vec![
+            Edit::Insert { id: NodeId(6), parent: NodeId(0), index: 2, data: "group".to_string() },
+            Edit::Move { id: NodeId(3), parent: NodeId(6), index: 0 },
+        ]);
+
+        // Unwrapping the group again moves the inner expression back out.
+        let edits = check(
+            "(binary (token 1) (token +) (group (unary (token -) (token 1))))",
+            expr,
+        );
+        assert_eq!(edits, vec![
+            Edit::Move { id: NodeId(4), parent: NodeId(0), index: 2 },
+            Edit::Delete { id: NodeId(3) },
+        ]);
+
+        check(expr, "(binary (token 2) (token *) (token 3))");
+        check(expr, "(unary (token -) (binary (token 1) (token +) (token 1)))");
+        check("(a (b) (c) (d) (e))", "(a (e) (d) (x (c)) (b))");
+        check("(a (b (c (d))))", "(a (d (c (b))))");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{Arena, NodeId};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+
+/// A change made to an `Arena`, reported to the listeners registered with
+/// `Arena::subscribe`.
+///
+/// Events are reported right after the change they describe has been made.
+/// Methods making several changes report an event for each of them, so
+/// adding a child reports both a `ChildAdded` and a `ParentChanged` event.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ArenaEvent {
+    /// A node was inserted into the arena.
+    NodeInserted { id: NodeId },
+    /// The last node of the arena was removed, by undoing its insertion.
+    NodeRemoved { id: NodeId },
+    /// A child was inserted into the children of `parent` at `index`.
+    ChildAdded { parent: NodeId, index: usize, child: NodeId },
+    /// A child was removed from the children of `parent` at `index`.
+    ChildRemoved { parent: NodeId, index: usize, child: NodeId },
+    /// The parent of a node was changed.
+    ParentChanged { id: NodeId, old: Option<NodeId>, new: Option<NodeId> },
+    /// The root of the arena was changed.
+    RootChanged { old: Option<NodeId>, new: Option<NodeId> },
+    /// The data of a node was replaced.
+    DataChanged { id: NodeId },
+}
+
+/// A handle to a listener registered with `Arena::subscribe`, which can be
+/// used to remove it again.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Subscription(usize);
+
+type Listener = Exclusive<Box<dyn FnMut(&ArenaEvent) + Send>>;
+
+/// The listeners registered on an `Arena`.
+#[derive(Default)]
+pub(crate) struct Listeners {
+    next: usize,
+    listeners: Vec<(Subscription, Listener)>,
+}
+
+impl Listeners {
+    #[cfg(feature = "rayon")]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+}
+
+mod exclusive {
+    /// A value which can only be reached through a mutable reference, like
+    /// the unstable `core::sync::Exclusive`. This lets an `Arena` be `Sync`
+    /// while its listeners are only `Send`.
+    pub(crate) struct Exclusive<T>(T);
+
+    impl<T> Exclusive<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(value)
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            &mut self.0
+        }
+    }
+
+    // SAFETY: the field of `Exclusive` is private to this module, and no
+    // method takes `&self`, so a shared reference to an `Exclusive` gives no
+    // access to the value at all. Sharing one between threads can therefore
+    // never let two threads reach the value at once, whatever `T` is.
+    unsafe impl<T> Sync for Exclusive<T> {}
+}
+
+use exclusive::Exclusive;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Registers a listener to be called with every change made to the
+    /// `Arena`, including changes made by undoing and redoing.
+    ///
+    /// Listeners are stored in the arena, and must be `Send` so that an
+    /// `Arena<T>` can still be moved to another thread whenever `T` can.
+    /// Listeners updating shared state can wrap it in a `Mutex`. They need
+    /// not be `Sync`, since they are only called while the arena is borrowed
+    /// mutably.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{Arena, ArenaEvent};
+    /// # use std::sync::{Arc, Mutex};
+    /// let arena = &mut Arena::new();
+    /// let root = arena.insert("+");
+    /// let one = arena.insert("1");
+    ///
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let subscription = arena.subscribe({
+    ///     let events = Arc::clone(&events);
+    ///     move |event| events.lock().unwrap().push(*event)
+    /// });
+    ///
+    /// root.add_child(arena, one);
+    /// assert_eq!(*events.lock().unwrap(), vec![
+    ///     ArenaEvent::ChildAdded { parent: root, index: 0, child: one },
+    ///     ArenaEvent::ParentChanged { id: one, old: None, new: Some(root) },
+    /// ]);
+    ///
+    /// assert!(arena.unsubscribe(subscription));
+    /// one.detach(arena);
+    /// assert_eq!(events.lock().unwrap().len(), 2);
+    /// ```
+    pub fn subscribe<F>(&mut self, listener: F) -> Subscription
+    where
+        F: FnMut(&ArenaEvent) + Send + 'static,
+    {
+        let listeners = &mut self.listeners;
+        let subscription = Subscription(listeners.next);
+        listeners.next += 1;
+        listeners.listeners.push((subscription, Exclusive::new(Box::new(listener))));
+        subscription
+    }
+
+    /// Removes a listener registered with `Arena::subscribe`, returning
+    /// `false` if it had already been removed.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let listeners = &mut self.listeners.listeners;
+        let len = listeners.len();
+        listeners.retain(|(other, _)| *other != subscription);
+        listeners.len() != len
+    }
+
+    /// Reports a change to every listener.
+    pub(crate) fn emit(&mut self, event: ArenaEvent) {
+        for (_, listener) in &mut self.listeners.listeners {
+            (listener.get_mut())(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_events() {
+        let arena = &mut Arena::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        let first = arena.subscribe({
+            let events = Arc::clone(&events);
+            move |event| events.lock().unwrap().push(*event)
+        });
+        // Listeners can own state which is not `Sync`.
+        let count = std::cell::Cell::new(0);
+        let second = arena.subscribe(move |_| count.set(count.get() + 1));
+
+        let root = arena.insert(1);
+        let two = arena.insert(2);
+        assert_eq!(take(), vec![
+            ArenaEvent::NodeInserted { id: root },
+            ArenaEvent::RootChanged { old: None, new: Some(root) },
+            ArenaEvent::NodeInserted { id: two },
+        ]);
+
+        arena.enable_journal();
+        root.add_child(arena, two);
+        two.set_data(arena, 3);
+        two.detach(arena);
+        two.set_parent(arena, root);
+        arena.set_root(two);
+        assert_eq!(take(), vec![
+            ArenaEvent::ChildAdded { parent: root, index: 0, child: two },
+            ArenaEvent::ParentChanged { id: two, old: None, new: Some(root) },
+            ArenaEvent::DataChanged { id: two },
+            ArenaEvent::ChildRemoved { parent: root, index: 0, child: two },
+            ArenaEvent::ParentChanged { id: two, old: Some(root), new: None },
+            ArenaEvent::ParentChanged { id: two, old: None, new: Some(root) },
+            ArenaEvent::RootChanged { old: Some(root), new: Some(two) },
+        ]);
+
+        // Undoing reports the changes reverting each step.
+        let three = arena.insert(3);
+        arena.undo();
+        arena.undo();
+        arena.undo();
+        assert_eq!(take(), vec![
+            ArenaEvent::NodeInserted { id: three },
+            ArenaEvent::NodeRemoved { id: three },
+            ArenaEvent::RootChanged { old: Some(two), new: Some(root) },
+            ArenaEvent::ParentChanged { id: two, old: Some(root), new: None },
+        ]);
+
+        assert!(arena.unsubscribe(second));
+        assert!(arena.unsubscribe(first));
+        assert!(!arena.unsubscribe(first));
+        arena.insert(4);
+        assert_eq!(take(), vec![]);
+    }
+
+    #[test]
+    fn test_listeners_keep_arena_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let mut arena = Arena::<i32>::new();
+        arena.subscribe(|_| {});
+        assert_send_sync(&arena);
+    }
+}
@@ -0,0 +1,42 @@
+// Trees shared by the tests of several modules.
+
+use crate::{Arena, NodeId};
+
+/// Builds the tree below, returning its root. Nodes are inserted in
+/// pre-order, so the node labelled `8` has the last `NodeId`.
+///
+/// ```text
+///         1
+///       / | \
+///      2  3  4
+///     /|     |
+///    5 6     7
+///            |
+///            8
+/// ```
+pub(crate) fn sample(arena: &mut Arena<i32>) -> NodeId {
+    tree!(arena, 1 => [2 => [5, 6], 3, 4 => [7 => [8]]])
+}
+
+/// Builds a chain of `len` nodes labelled from `0` at the top, returning the
+/// top node.
+pub(crate) fn chain(arena: &mut Arena<i32>, len: i32) -> NodeId {
+    let top = arena.insert(0);
+    let mut bottom = top;
+
+    for label in 1..len {
+        let id = arena.insert(label);
+        bottom.add_child(arena, id);
+        bottom = id;
+    }
+
+    top
+}
+
+/// Returns the `NodeId` of the first node labelled `label`.
+pub(crate) fn find(arena: &Arena<i32>, label: i32) -> NodeId {
+    (0..arena.len())
+        .map(NodeId)
+        .find(|&id| *arena.get(id).data() == label)
+        .expect("label should be in the arena")
+}
@@ -0,0 +1,174 @@
+use crate::{Arena, Node, NodeId, Tree};
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Consumes the `Arena`, packing it into a read-only `FrozenTree` with
+    /// the same `NodeId`s.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena, Tree};
+    /// let mut arena = Arena::new();
+    /// let root = tree!(&mut arena, "+" => ["1", "*" => ["2", "3"]]);
+    ///
+    /// let tree = arena.freeze();
+    /// let times = tree.children(root)[1];
+    /// assert_eq!(tree.data(times), Some(&"*"));
+    /// assert_eq!(tree.parent(times), Some(root));
+    /// assert_eq!(tree.descendants(root).count(), 5);
+    ///
+    /// let arena = tree.thaw();
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (* (2) (3)))");
+    /// ```
+    pub fn freeze(self) -> FrozenTree<T> {
+        let mut tree = FrozenTree {
+            data: Vec::with_capacity(self.len()),
+            parents: Vec::with_capacity(self.len()),
+            offsets: Vec::with_capacity(self.len() + 1),
+            children: Vec::new(),
+            root: self.root,
+        };
+
+        tree.offsets.push(0);
+        for node in self.nodes {
+            tree.children.extend(node.children);
+            tree.offsets.push(tree.children.len());
+            tree.parents.push(node.parent);
+            tree.data.push(node.data);
+        }
+
+        tree
+    }
+}
+
+/// A read-only tree stored in compressed sparse row form.
+///
+/// The children of every node are packed into a single vector, in order of
+/// their parents, and the children of the node at index `i` are found
+/// between `offsets[i]` and `offsets[i + 1]`. Compared with an `Arena`, this
+/// saves the `Vec` of children and the `NodeId` kept by every `Node`.
+///
+/// A `FrozenTree` is created with `Arena::freeze`, and can be turned back
+/// into an `Arena` with `FrozenTree::thaw`. It is read through the `Tree`
+/// trait, so the methods of `NodeId` reading a tree work on it as well.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrozenTree<T> {
+    data: Vec<T>,
+    parents: Vec<Option<NodeId>>,
+    offsets: Vec<usize>,
+    children: Vec<NodeId>,
+    root: Option<NodeId>,
+}
+
+impl<T> Tree for FrozenTree<T> {
+    type Data = T;
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    fn data(&self, id: NodeId) -> Option<&T> {
+        self.data.get(id.index())
+    }
+
+    fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.children[self.offsets[id.index()]..self.offsets[id.index() + 1]]
+    }
+}
+
+impl<T> FrozenTree<T> {
+    /// Consumes the tree, unpacking it into an `Arena` with the same
+    /// `NodeId`s.
+    pub fn thaw(self) -> Arena<T> {
+        let Self { data, parents, offsets, children, root } = self;
+
+        let nodes = data.into_iter()
+            .zip(parents)
+            .zip(offsets.windows(2))
+            .enumerate()
+            .map(|(index, ((data, parent), range))| {
+                Node::with(NodeId(index), data, parent, children[range[0]..range[1]].to_vec())
+            })
+            .collect();
+
+        Arena { nodes, root, ..Arena::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, find};
+
+    #[test]
+    fn test_freeze() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let detached = arena.insert(9);
+        let [two, three, four, six, seven] = [2, 3, 4, 6, 7].map(|label| find(arena, label));
+        let original = arena.nodes();
+
+        let tree = std::mem::take(arena).freeze();
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.root(), Some(root));
+        assert_eq!(root.children(&tree), &[two, three, four]);
+        assert_eq!(three.children(&tree), &[]);
+        assert_eq!(tree.children(detached), &[]);
+        assert_eq!(tree.parent(detached), None);
+        assert_eq!(tree.ancestors(seven).collect::<Vec<_>>(), vec![four, root]);
+        assert_eq!(tree.lca(seven, six), Some(root));
+        assert_eq!(tree.to_sexpr(root), "(1 (2 (5) (6)) (3) (4 (7 (8))))");
+
+        let values: Vec<i32> = tree.descendants(root).map(|id| *tree.data(id).unwrap()).collect();
+        assert_eq!(values, vec![1, 2, 5, 6, 3, 4, 7, 8]);
+        assert_eq!(tree.iter().map(|(_, &data)| data).sum::<i32>(), 45);
+
+        let thawed = tree.clone().thaw();
+        assert_eq!(thawed.nodes(), original);
+        assert_eq!(thawed.root(), Some(root));
+        assert_eq!(thawed.freeze(), tree);
+    }
+
+    #[test]
+    fn test_freeze_edge_cases() {
+        // An empty arena has a single offset and no root.
+        let tree = Arena::<i32>::new().freeze();
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), None);
+        assert_eq!(tree.data(NodeId(0)), None);
+        assert_eq!(tree.offsets, [0]);
+
+        // Children keep their order, not their index order, and a root other
+        // than the first node is kept.
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let [two, three, four, eight] = [2, 3, 4, 8].map(|label| find(arena, label));
+        two.detach(arena);
+        eight.detach(arena);
+        root.add_child(arena, two);
+        four.insert_child(arena, 0, eight);
+        arena.set_root(four);
+
+        let tree = std::mem::take(arena).freeze();
+        assert_eq!(tree.root(), Some(four));
+        assert_eq!(tree.to_sexpr(four), "(4 (8) (7))");
+        assert_eq!(tree.to_sexpr(root), "(1 (3) (4 (8) (7)) (2 (5) (6)))");
+
+        // Leaves between other nodes have empty ranges of children.
+        assert_eq!(tree.offsets[three.index()], tree.offsets[three.index() + 1]);
+        assert_eq!(tree.clone().thaw().freeze(), tree);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::collections::Map;
+use crate::subtree::combine;
+use crate::{Arena, NodeId};
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+/// An `Arena` wrapper that shares structurally identical nodes.
+///
+/// Nodes are inserted together with their children. Inserting a node whose
+/// data and child `NodeId`s are identical to those of a node inserted earlier
+/// returns the existing `NodeId` instead of allocating a new node. Since
+/// children are themselves shared, identical subtrees are only ever stored
+/// once, turning the arena into a DAG (like the green trees of a
+/// red-green syntax tree).
+///
+/// A shared node can be the child of several nodes, but can only store one
+/// parent. Its parent is the first node it was added to as a child, so parent
+/// links should not be relied upon when traversing a hash-consed arena.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::HashConsArena;
+/// let mut arena = HashConsArena::new();
+///
+/// // 1 + 1
+/// let one = arena.insert("1", vec![]);
+/// let plus = arena.insert("+", vec![]);
+/// let lhs = arena.insert("binary", vec![one, plus, one]);
+///
+/// // (1 + 1) + (1 + 1)
+/// let rhs = arena.insert("binary", vec![one, plus, one]);
+/// let root = arena.insert("binary", vec![lhs, plus, rhs]);
+///
+/// assert_eq!(lhs, rhs);
+/// assert_eq!(arena.len(), 4);
+/// assert_eq!(root.children(arena.arena()), &vec![lhs, plus, lhs]);
+/// ```
+pub struct HashConsArena<T> {
+    arena: Arena<T>,
+    table: Map<u64, Vec<NodeId>>,
+}
+
+impl<T> HashConsArena<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates a new empty `HashConsArena`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns `true` if no nodes have been inserted into the arena yet.
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Returns a reference to the underlying `Arena`.
+    pub fn arena(&self) -> &Arena<T> {
+        &self.arena
+    }
+
+    /// Consumes the `HashConsArena`, returning the underlying `Arena`.
+    pub fn into_arena(self) -> Arena<T> {
+        self.arena
+    }
+
+    /// Sets a new root for the underlying `Arena`.
+    ///
+    /// Since nodes are usually inserted bottom-up, the first node to be
+    /// inserted (and therefore the default root) is typically a leaf.
+    pub fn set_root<OptionalId>(&mut self, root: OptionalId)
+    where
+        OptionalId: Into<Option<NodeId>>,
+    {
+        self.arena.set_root(root)
+    }
+
+    /// Returns the `NodeId` of an existing node with the given data and
+    /// children, if there is one.
+    pub fn find(&self, data: &T, children: &[NodeId]) -> Option<NodeId> {
+        self.table.get(&combine(data, children))?
+            .iter()
+            .copied()
+            .find(|&id| {
+                let node = self.arena.get(id);
+                node.data() == data && node.children() == children
+            })
+    }
+
+    /// Inserts a node with the given data and children, unless an identical
+    /// node already exists, and returns its `NodeId`.
+    pub fn insert<I>(&mut self, data: T, children: I) -> NodeId
+    where
+        I: IntoIterator<Item = NodeId>,
+    {
+        let children: Vec<NodeId> = children.into_iter().collect();
+
+        if let Some(id) = self.find(&data, &children) {
+            return id;
+        }
+
+        let key = combine(&data, &children);
+        let id = self.arena.insert(data);
+
+        for child in children {
+            self.arena.get_mut(id).add_child(child);
+
+            let child = self.arena.get_mut(child);
+            if child.parent().is_none() {
+                child.set_parent(id);
+            }
+        }
+
+        self.arena.refresh_caches(id);
+        self.table.entry(key).or_default().push(id);
+        id
+    }
+}
+
+impl<T> Default for HashConsArena<T> {
+    fn default() -> Self {
+        Self { arena: Arena::new(), table: Map::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_cons_arena() {
+        // a * (a * a)
+        let mut arena = HashConsArena::new();
+
+        let a = arena.insert("a", vec![]);
+        let times = arena.insert("*", vec![]);
+        let inner = arena.insert("binary", vec![a, times, a]);
+        let outer = arena.insert("binary", vec![a, times, inner]);
+        arena.set_root(outer);
+
+        assert_ne!(inner, outer);
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.insert("a", vec![]), a);
+        assert_eq!(arena.insert("binary", vec![a, times, a]), inner);
+        assert_eq!(arena.find(&"binary", &[a, times, inner]), Some(outer));
+        assert_eq!(arena.find(&"binary", &[inner, times, a]), None);
+        assert_eq!(arena.len(), 4);
+
+        // Shared nodes keep their first parent.
+        assert_eq!(a.parent(arena.arena()), Some(inner));
+        assert_eq!(inner.parent(arena.arena()), Some(outer));
+
+        let arena = arena.into_arena();
+        assert_eq!(arena.root(), Some(outer));
+        assert_eq!(
+            arena.to_sexpr(outer),
+            "(binary (a) (*) (binary (a) (*) (a)))",
+        );
+    }
+}
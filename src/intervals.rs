@@ -0,0 +1,189 @@
+use crate::{Arena, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::ops::Range;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Numbers the tree starting at `root` in pre-order, returning an
+    /// `IntervalIndex` that answers ancestor queries in constant time.
+    ///
+    /// The index is a snapshot of the tree at the time it was built, and is
+    /// not updated if the arena is mutated afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4", "5"]]);
+    /// let index = arena.freeze_intervals(root);
+    ///
+    /// let str_2 = root.children(arena)[0];
+    /// let str_3 = root.children(arena)[1];
+    /// let str_4 = str_3.children(arena)[0];
+    /// let str_5 = str_3.children(arena)[1];
+    ///
+    /// assert!(index.is_ancestor(root, str_4));
+    /// assert!(index.is_ancestor(str_3, str_5));
+    /// assert!(!index.is_ancestor(str_2, str_5));
+    /// assert_eq!(index.descendants(str_3), &[str_3, str_4, str_5]);
+    /// ```
+    pub fn freeze_intervals(&self, root: NodeId) -> IntervalIndex {
+        let order: Vec<NodeId> = self.preorder(root).map(|(id, _)| id).collect();
+        let mut entries = vec![None; self.len()];
+        let mut exits = vec![0; self.len()];
+
+        for (entry, &id) in order.iter().enumerate() {
+            entries[id.index()] = Some(entry);
+        }
+
+        // Walking the pre-order backwards visits children before parents, so
+        // the exit of a node is just past the exit of its last child.
+        for (entry, &id) in order.iter().enumerate().rev() {
+            exits[id.index()] = match self.get(id).children().last() {
+                Some(child) => exits[child.index()],
+                None => entry + 1,
+            };
+        }
+
+        IntervalIndex { order, entries, exits }
+    }
+}
+
+/// A pre-order numbering of a tree, built with `Arena::freeze_intervals`.
+///
+/// Every node in the tree is given an entry number, which is its position in
+/// a pre-order traversal, and an exit number, which is one past the entry
+/// number of its last descendant. The descendants of a node therefore form
+/// the contiguous range between its entry and exit numbers.
+#[derive(Clone, Debug)]
+pub struct IntervalIndex {
+    order: Vec<NodeId>,
+    entries: Vec<Option<usize>>,
+    exits: Vec<usize>,
+}
+
+impl IntervalIndex {
+    /// Returns the number of nodes in the indexed tree.
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns `true` if the indexed tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Returns `true` if the node at `id` is part of the indexed tree.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.entry(id).is_some()
+    }
+
+    /// Returns the pre-order position of the node at `id`.
+    pub fn entry(&self, id: NodeId) -> Option<usize> {
+        *self.entries.get(id.index())?
+    }
+
+    /// Returns the range of pre-order positions covered by the subtree at
+    /// `id`.
+    pub fn interval(&self, id: NodeId) -> Option<Range<usize>> {
+        let entry = self.entry(id)?;
+        Some(entry..self.exits[id.index()])
+    }
+
+    /// Returns `true` if `ancestor` is `descendant` itself or one of its
+    /// ancestors.
+    ///
+    /// Returns `false` if either node is not part of the indexed tree.
+    pub fn is_ancestor(&self, ancestor: NodeId, descendant: NodeId) -> bool {
+        match (self.interval(ancestor), self.entry(descendant)) {
+            (Some(interval), Some(entry)) => interval.contains(&entry),
+            _ => false,
+        }
+    }
+
+    /// Returns the nodes of the subtree at `id` in pre-order, starting with
+    /// `id` itself.
+    ///
+    /// Returns an empty slice if the node is not part of the indexed tree.
+    pub fn descendants(&self, id: NodeId) -> &[NodeId] {
+        match self.interval(id) {
+            Some(interval) => &self.order[interval],
+            None => &[],
+        }
+    }
+
+    /// Returns every node of the indexed tree in pre-order.
+    pub fn preorder(&self) -> &[NodeId] {
+        &self.order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_interval_index() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let other = tree!(arena, 9 => [10]);
+        let index = arena.freeze_intervals(root);
+
+        assert_eq!(index.len(), 8);
+        assert!(!index.contains(other));
+        assert_eq!(index.interval(root), Some(0..8));
+        assert_eq!(index.descendants(other), &[]);
+
+        for &a in index.preorder() {
+            let descendants: Vec<NodeId> = arena.preorder(a).map(|(id, _)| id).collect();
+            assert_eq!(index.descendants(a), descendants.as_slice());
+
+            for &b in index.preorder() {
+                assert_eq!(index.is_ancestor(a, b), arena.lca(a, b) == Some(a));
+            }
+            assert!(!index.is_ancestor(a, other));
+            assert!(!index.is_ancestor(other, a));
+        }
+    }
+
+    #[test]
+    fn test_interval_index_edge_cases() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let [two, three, four, five, six, eight] = [2, 3, 4, 5, 6, 8].map(|label| fixtures::find(arena, label));
+        let index = arena.freeze_intervals(root);
+
+        // Siblings cover touching intervals, one ending where the next
+        // starts, without either containing the other.
+        assert_eq!(index.interval(five), Some(2..3));
+        assert_eq!(index.interval(six), Some(3..4));
+        assert_eq!(index.interval(two), Some(1..4));
+        assert_eq!(index.interval(three), Some(4..5));
+        assert!(!index.is_ancestor(two, three));
+        assert!(!index.is_ancestor(five, six));
+        assert!(!index.is_ancestor(three, four));
+
+        // The last leaf ends the interval of every one of its ancestors.
+        assert_eq!(index.interval(eight), Some(7..8));
+        assert_eq!(index.interval(four), Some(5..8));
+
+        // Nodes outside the index, even past the end of the arena, have no
+        // interval and an empty subtree.
+        let outside = NodeId(arena.len());
+        assert_eq!(index.interval(outside), None);
+        assert_eq!(index.descendants(outside), &[]);
+        assert!(!index.is_ancestor(outside, outside));
+
+        // Indexing a leaf gives a single interval of one node.
+        let index = arena.freeze_intervals(five);
+        assert_eq!(index.interval(five), Some(0..1));
+        assert_eq!(index.descendants(five), &[five]);
+        assert!(!index.contains(two));
+    }
+}
@@ -0,0 +1,375 @@
+use crate::{Arena, ArenaEvent, NodeId};
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::mem;
+
+/// A reversible change to an `Arena`.
+///
+/// Applying an operation returns the operation which reverts it, so the same
+/// code serves undo, redo and rollback.
+pub(crate) enum Op<T> {
+    Push(T),
+    Pop,
+    InsertChild { parent: NodeId, index: usize, child: NodeId },
+    RemoveChild { parent: NodeId, index: usize },
+    SetParent { id: NodeId, parent: Option<NodeId> },
+    SetRoot { root: Option<NodeId> },
+    SetData { id: NodeId, data: T },
+}
+
+/// The undo and redo history of an `Arena`.
+///
+/// Every step holds the operations reverting it, in the order they were
+/// recorded, and is reverted by applying them from last to first.
+pub(crate) struct Journal<T> {
+    pub(crate) clone: fn(&T) -> T,
+    undo: Vec<Vec<Op<T>>>,
+    redo: Vec<Vec<Op<T>>>,
+    transaction: Option<Vec<Op<T>>>,
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Starts recording changes to the `Arena`, so that they can be undone
+    /// and redone.
+    ///
+    /// Every call to a method mutating the arena, such as `Arena::insert` or
+    /// `NodeId::add_child`, is recorded as a separate step. Related changes
+    /// can be grouped into a single step with `Arena::begin_transaction` and
+    /// `Arena::commit`.
+    ///
+    /// The journal needs to keep the previous data of nodes whose data is
+    /// replaced, which requires `T: Clone` when enabling it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "+" => ["1", "2"]);
+    /// arena.enable_journal();
+    ///
+    /// // 1 + (2 * 3)
+    /// arena.begin_transaction();
+    /// let two = root.children(arena)[1];
+    /// let times = arena.insert("*");
+    /// let three = arena.insert("3");
+    /// two.detach(arena);
+    /// root.add_child(arena, times);
+    /// times.add_child(arena, two).add_child(arena, three);
+    /// arena.commit();
+    ///
+    /// // 1 + (4 * 3)
+    /// two.set_data(arena, "4");
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (* (4) (3)))");
+    ///
+    /// assert!(arena.undo());
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (* (2) (3)))");
+    /// assert!(arena.undo());
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+    /// assert_eq!(arena.len(), 3);
+    ///
+    /// assert!(arena.redo());
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (* (2) (3)))");
+    /// ```
+    pub fn enable_journal(&mut self)
+    where
+        T: Clone,
+    {
+        if self.journal.is_none() {
+            self.journal = Some(Journal {
+                clone: T::clone,
+                undo: Vec::new(),
+                redo: Vec::new(),
+                transaction: None,
+            });
+        }
+    }
+
+    /// Stops recording changes to the `Arena`, discarding its history.
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
+
+    /// Returns `true` if the `Arena` records changes made to it.
+    pub fn has_journal(&self) -> bool {
+        self.journal.is_some()
+    }
+
+    /// Starts a transaction, grouping every change made until the next call
+    /// to `Arena::commit` into a single undo step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the journal has not been enabled, or if a transaction is
+    /// already in progress.
+    pub fn begin_transaction(&mut self) {
+        let journal = self.journal.as_mut().expect("journal should be enabled");
+        assert!(journal.transaction.is_none(), "transaction already in progress");
+        journal.transaction = Some(Vec::new());
+    }
+
+    /// Ends the current transaction, recording its changes as a single undo
+    /// step. Transactions without any changes are discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no transaction is in progress.
+    pub fn commit(&mut self) {
+        let journal = self.journal.as_mut().expect("journal should be enabled");
+        let ops = journal.transaction.take().expect("no transaction in progress");
+
+        if !ops.is_empty() {
+            journal.undo.push(ops);
+            journal.redo.clear();
+        }
+    }
+
+    /// Ends the current transaction, reverting every change made since it
+    /// began.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no transaction is in progress.
+    pub fn rollback(&mut self) {
+        let journal = self.journal.as_mut().expect("journal should be enabled");
+        let ops = journal.transaction.take().expect("no transaction in progress");
+        self.revert(ops);
+    }
+
+    /// Reverts the last recorded step, returning `false` if there is nothing
+    /// to undo.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is in progress.
+    pub fn undo(&mut self) -> bool {
+        let ops = match self.journal.as_mut().and_then(Journal::pop_undo) {
+            Some(ops) => ops,
+            None => return false,
+        };
+
+        let redo = self.revert(ops);
+        if let Some(journal) = &mut self.journal {
+            journal.redo.push(redo);
+        }
+        true
+    }
+
+    /// Reapplies the last undone step, returning `false` if there is nothing
+    /// to redo.
+    ///
+    /// Recording a new step after undoing discards every step that could
+    /// have been redone.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a transaction is in progress.
+    pub fn redo(&mut self) -> bool {
+        let ops = match self.journal.as_mut().and_then(Journal::pop_redo) {
+            Some(ops) => ops,
+            None => return false,
+        };
+
+        let undo = self.revert(ops);
+        if let Some(journal) = &mut self.journal {
+            journal.undo.push(undo);
+        }
+        true
+    }
+
+    /// Runs `f`, recording every change it makes as a single undo step. If a
+    /// transaction is already in progress, the changes become part of it.
+    pub(crate) fn grouped<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let group = matches!(&self.journal, Some(journal) if journal.transaction.is_none());
+
+        if group {
+            self.begin_transaction();
+        }
+        let result = f(self);
+        if group {
+            self.commit();
+        }
+
+        result
+    }
+
+    /// Records the operations reverting a change, if the journal is enabled.
+    pub(crate) fn record<const N: usize>(&mut self, ops: [Op<T>; N]) {
+        let journal = match &mut self.journal {
+            Some(journal) => journal,
+            None => return,
+        };
+
+        match &mut journal.transaction {
+            Some(transaction) => transaction.extend(ops),
+            None => {
+                journal.undo.push(ops.into());
+                journal.redo.clear();
+            },
+        }
+    }
+
+    /// Applies a step's operations from last to first, returning the step
+    /// reverting them.
+    fn revert(&mut self, ops: Vec<Op<T>>) -> Vec<Op<T>> {
+        ops.into_iter().rev().map(|op| self.apply(op)).collect()
+    }
+
+    /// Applies an operation without recording it, returning its inverse.
+    fn apply(&mut self, op: Op<T>) -> Op<T> {
+        match op {
+            Op::Push(data) => {
+                let id = self.push_node(data);
+                self.emit(ArenaEvent::NodeInserted { id });
+                Op::Pop
+            },
+            Op::Pop => {
+                let node = self.nodes.pop().expect("arena should not be empty");
+                if let Some(digests) = &mut self.digests {
+                    digests.pop();
+                }
+                if let Some(sizes) = &mut self.sizes {
+                    sizes.pop();
+                }
+                self.emit(ArenaEvent::NodeRemoved { id: node.id });
+                Op::Push(node.data)
+            },
+            Op::InsertChild { parent, index, child } => {
+                self.get_mut(parent).insert_child(index, child);
+                self.refresh_caches(parent);
+                self.emit(ArenaEvent::ChildAdded { parent, index, child });
+                Op::RemoveChild { parent, index }
+            },
+            Op::RemoveChild { parent, index } => {
+                let child = self.get_mut(parent).children.remove(index);
+                self.refresh_caches(parent);
+                self.emit(ArenaEvent::ChildRemoved { parent, index, child });
+                Op::InsertChild { parent, index, child }
+            },
+            Op::SetParent { id, parent } => {
+                let old = mem::replace(&mut self.get_mut(id).parent, parent);
+                self.emit(ArenaEvent::ParentChanged { id, old, new: parent });
+                Op::SetParent { id, parent: old }
+            },
+            Op::SetRoot { root } => {
+                let old = mem::replace(&mut self.root, root);
+                self.emit(ArenaEvent::RootChanged { old, new: root });
+                Op::SetRoot { root: old }
+            },
+            Op::SetData { id, data } => {
+                let data = mem::replace(&mut self.get_mut(id).data, data);
+                self.refresh_caches(id);
+                self.emit(ArenaEvent::DataChanged { id });
+                Op::SetData { id, data }
+            },
+        }
+    }
+}
+
+impl<T> Journal<T> {
+    fn pop_undo(&mut self) -> Option<Vec<Op<T>>> {
+        assert!(self.transaction.is_none(), "transaction in progress");
+        self.undo.pop()
+    }
+
+    fn pop_redo(&mut self) -> Option<Vec<Op<T>>> {
+        assert!(self.transaction.is_none(), "transaction in progress");
+        self.redo.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let arena = &mut Arena::new();
+        arena.enable_journal();
+        arena.enable_digests();
+        arena.enable_subtree_sizes();
+
+        let check = |arena: &Arena<i32>| {
+            for index in 0..arena.len() {
+                let id = NodeId(index);
+                assert_eq!(arena.subtree_digest(id), arena.subtree_hash(id));
+                assert_eq!(arena.subtree_size(id), arena.preorder(id).count());
+            }
+        };
+
+        let root = tree!(arena, 1 => [2, 3 => [4]]);
+        let initial = arena.nodes();
+        let three = root.children(arena)[1];
+
+        arena.begin_transaction();
+        let five = arena.insert(5);
+        three.detach(arena);
+        five.add_child(arena, three);
+        root.insert_child(arena, 0, five);
+        three.set_data(arena, 6);
+        arena.set_root(five);
+        arena.commit();
+
+        let edited = arena.nodes();
+        assert_eq!(arena.to_sexpr(root), "(1 (5 (6 (4))) (2))");
+        check(arena);
+
+        // Undoing and redoing the transaction moves between both states.
+        assert!(arena.undo());
+        assert_eq!(arena.nodes(), initial);
+        assert_eq!(arena.root(), Some(root));
+        check(arena);
+        assert!(arena.redo());
+        assert_eq!(arena.nodes(), edited);
+        assert_eq!(arena.root(), Some(five));
+        check(arena);
+        assert!(!arena.redo());
+
+        // Rolling back a transaction reverts it without recording anything.
+        arena.begin_transaction();
+        let seven = arena.insert(7);
+        five.add_child(arena, seven);
+        five.set_parent(arena, seven);
+        arena.rollback();
+        assert_eq!(arena.nodes(), edited);
+        check(arena);
+
+        // Outside of transactions, every change is a separate step.
+        let eight = arena.insert(8);
+        root.add_child(arena, eight);
+        assert!(arena.undo());
+        assert_eq!(arena.len(), edited.len() + 1);
+        assert!(arena.undo());
+        assert_eq!(arena.nodes(), edited);
+
+        // Recording a new step discards the steps that could be redone.
+        arena.set_root(root);
+        assert!(!arena.redo());
+
+        while arena.undo() {}
+        assert!(arena.is_empty());
+        assert_eq!(arena.root(), None);
+
+        arena.disable_journal();
+        assert!(!arena.redo());
+    }
+
+    #[test]
+    fn test_undo_first_insert() {
+        // Inserting the first node also makes it the root, in a single step.
+        let arena = &mut Arena::new();
+        arena.enable_journal();
+        arena.insert(1);
+
+        assert!(arena.undo());
+        assert!(arena.is_empty());
+        assert_eq!(arena.root(), None);
+        assert!(!arena.undo());
+
+        assert!(arena.redo());
+        assert_eq!(arena.root(), Some(NodeId(0)));
+    }
+}
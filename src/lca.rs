@@ -0,0 +1,289 @@
+use crate::{Arena, NodeId, Tree};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::cmp;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the depth of the node at `id`, which is the number of parent
+    /// links between it and the top of its tree.
+    pub fn depth(&self, id: NodeId) -> usize {
+        depth(self, id)
+    }
+
+    /// Returns the lowest common ancestor of two nodes, which is the deepest
+    /// node that has both of them as descendants. A node is considered to be
+    /// a descendant of itself.
+    ///
+    /// This walks the parent links of both nodes, taking time proportional to
+    /// their depth. Use `Arena::lca_index` to answer many queries on a tree
+    /// that no longer changes.
+    ///
+    /// Returns `None` if the nodes are not part of the same tree.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4", "5"]]);
+    ///
+    /// let str_2 = root.children(arena)[0];
+    /// let str_3 = root.children(arena)[1];
+    /// let str_4 = str_3.children(arena)[0];
+    /// let str_5 = str_3.children(arena)[1];
+    ///
+    /// assert_eq!(arena.lca(str_4, str_5), Some(str_3));
+    /// assert_eq!(arena.lca(str_2, str_5), Some(root));
+    /// assert_eq!(arena.lca(str_3, str_4), Some(str_3));
+    /// assert_eq!(arena.distance(str_2, str_5), Some(3));
+    /// ```
+    pub fn lca(&self, a: NodeId, b: NodeId) -> Option<NodeId> {
+        lca(self, a, b)
+    }
+
+    /// Returns the number of edges on the path between two nodes.
+    ///
+    /// Returns `None` if the nodes are not part of the same tree.
+    pub fn distance(&self, a: NodeId, b: NodeId) -> Option<usize> {
+        let ancestor = self.lca(a, b)?;
+        Some(self.depth(a) + self.depth(b) - 2 * self.depth(ancestor))
+    }
+
+    /// Builds an `LcaIndex` for the tree starting at `root`, which answers
+    /// lowest common ancestor queries in constant time.
+    ///
+    /// The index is a snapshot of the tree at the time it was built, and is
+    /// not updated if the arena is mutated afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4", "5"]]);
+    /// let index = arena.lca_index(root);
+    ///
+    /// let str_2 = root.children(arena)[0];
+    /// let str_3 = root.children(arena)[1];
+    /// let str_5 = str_3.children(arena)[1];
+    ///
+    /// assert_eq!(index.lca(str_2, str_5), Some(root));
+    /// assert_eq!(index.distance(str_2, str_5), Some(3));
+    /// assert_eq!(index.depth(str_5), Some(2));
+    /// ```
+    pub fn lca_index(&self, root: NodeId) -> LcaIndex {
+        let mut euler = Vec::new();
+        let mut first = vec![None; self.len()];
+        let mut stack = vec![(root, 0)];
+
+        first[root.index()] = Some(0);
+        euler.push((0, root));
+
+        while let Some((id, next)) = stack.last_mut() {
+            let id = *id;
+            let children = self.get(id).children();
+
+            if let Some(&child) = children.get(*next) {
+                *next += 1;
+                first[child.index()] = Some(euler.len());
+                euler.push((stack.len(), child));
+                stack.push((child, 0));
+            } else {
+                stack.pop();
+                if let Some(&(parent, _)) = stack.last() {
+                    euler.push((stack.len() - 1, parent));
+                }
+            }
+        }
+
+        assert!(euler.len() <= u32::MAX as usize, "tree should fit in a `u32` Euler tour");
+
+        // `table[levels[k] + i]` holds the position of the shallowest entry
+        // of `euler[i..i + 2^k]`.
+        let mut table: Vec<u32> = (0..euler.len() as u32).collect();
+        let mut levels = vec![0];
+        let mut width = 1;
+
+        while 2 * width <= euler.len() {
+            let previous = levels[levels.len() - 1];
+            levels.push(table.len());
+
+            for i in 0..=euler.len() - 2 * width {
+                let entry = shallowest(&euler, table[previous + i], table[previous + i + width]);
+                table.push(entry);
+            }
+            width *= 2;
+        }
+
+        LcaIndex { first, euler, table, levels }
+    }
+}
+
+/// Returns the number of parent links between the node at `id` and the top
+/// of its tree.
+pub(crate) fn depth<A>(tree: &A, id: NodeId) -> usize
+where
+    A: Tree + ?Sized,
+{
+    tree.ancestors(id).count()
+}
+
+/// Returns the lowest common ancestor of two nodes by walking up from the
+/// deeper one until both meet.
+pub(crate) fn lca<A>(tree: &A, a: NodeId, b: NodeId) -> Option<NodeId>
+where
+    A: Tree + ?Sized,
+{
+    let mut a_depth = depth(tree, a);
+    let mut b_depth = depth(tree, b);
+    let (mut a, mut b) = (a, b);
+
+    while a_depth > b_depth {
+        a = tree.parent(a)?;
+        a_depth -= 1;
+    }
+
+    while b_depth > a_depth {
+        b = tree.parent(b)?;
+        b_depth -= 1;
+    }
+
+    while a != b {
+        a = tree.parent(a)?;
+        b = tree.parent(b)?;
+    }
+
+    Some(a)
+}
+
+/// A precomputed index answering lowest common ancestor queries on a tree in
+/// constant time, built with `Arena::lca_index`.
+///
+/// The index holds an Euler tour of the tree, recording every node each time
+/// the tour passes through it, and a sparse table of the shallowest node in
+/// every power-of-two-sized range of the tour. The lowest common ancestor of
+/// two nodes is the shallowest node visited between their first occurrences.
+///
+/// The levels of the sparse table are stored one after another in a single
+/// vector of `u32` positions into the tour, which takes `8n log2(2n)` bytes
+/// for a tree of `n` nodes.
+#[derive(Clone, Debug)]
+pub struct LcaIndex {
+    first: Vec<Option<usize>>,
+    euler: Vec<(usize, NodeId)>,
+    table: Vec<u32>,
+    levels: Vec<usize>,
+}
+
+impl LcaIndex {
+    /// Returns the depth of the node at `id` below the root of the index.
+    ///
+    /// Returns `None` if the node is not part of the indexed tree.
+    pub fn depth(&self, id: NodeId) -> Option<usize> {
+        Some(self.euler[self.position(id)?].0)
+    }
+
+    /// Returns the lowest common ancestor of two nodes.
+    ///
+    /// Returns `None` if either node is not part of the indexed tree.
+    pub fn lca(&self, a: NodeId, b: NodeId) -> Option<NodeId> {
+        let a = self.position(a)?;
+        let b = self.position(b)?;
+        let (start, end) = (a.min(b), a.max(b) + 1);
+
+        let level = (usize::BITS - (end - start).leading_zeros() - 1) as usize;
+        let width = 1 << level;
+        let offset = self.levels[level];
+        let entry = shallowest(&self.euler, self.table[offset + start], self.table[offset + end - width]);
+
+        Some(self.euler[entry as usize].1)
+    }
+
+    /// Returns the number of edges on the path between two nodes.
+    ///
+    /// Returns `None` if either node is not part of the indexed tree.
+    pub fn distance(&self, a: NodeId, b: NodeId) -> Option<usize> {
+        let ancestor = self.lca(a, b)?;
+        Some(self.depth(a)? + self.depth(b)? - 2 * self.depth(ancestor)?)
+    }
+
+    fn position(&self, id: NodeId) -> Option<usize> {
+        *self.first.get(id.index())?
+    }
+}
+
+/// Returns whichever of two positions in the tour holds the shallower node.
+fn shallowest(euler: &[(usize, NodeId)], a: u32, b: u32) -> u32 {
+    cmp::min_by_key(a, b, |&entry| euler[entry as usize].0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_lca() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let other = tree!(arena, 9 => [10]);
+        let index = arena.lca_index(root);
+        let find = |label| fixtures::find(arena, label);
+
+        assert_eq!(arena.lca(find(5), find(6)), Some(find(2)));
+        assert_eq!(arena.lca(find(5), find(8)), Some(root));
+        assert_eq!(arena.lca(find(8), find(4)), Some(find(4)));
+        assert_eq!(arena.distance(find(6), find(8)), Some(5));
+        assert_eq!(arena.lca(find(8), other), None);
+        assert_eq!(arena.distance(find(8), other), None);
+
+        let ids: Vec<NodeId> = (1..=8).map(find).collect();
+        for &a in &ids {
+            for &b in &ids {
+                assert_eq!(index.lca(a, b), arena.lca(a, b));
+                assert_eq!(index.distance(a, b), arena.distance(a, b));
+            }
+            assert_eq!(index.depth(a), Some(arena.depth(a)));
+            assert_eq!(index.lca(a, other), None);
+        }
+    }
+
+    #[test]
+    fn test_lca_edge_cases() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let [two, four, seven, eight] = [2, 4, 7, 8].map(|label| fixtures::find(arena, label));
+
+        // A node is its own lowest common ancestor.
+        assert_eq!(arena.lca(seven, seven), Some(seven));
+        assert_eq!(arena.distance(seven, seven), Some(0));
+        assert_eq!(arena.lca_index(root).lca(seven, seven), Some(seven));
+
+        // Detaching a subtree disconnects it from the rest of the tree.
+        four.detach(arena);
+        assert_eq!(arena.lca(eight, two), None);
+        assert_eq!(arena.lca(eight, four), Some(four));
+        assert_eq!(arena.lca(root, root), Some(root));
+
+        // An index over a single node or a subtree only knows its own nodes,
+        // with depths measured from its root.
+        let index = arena.lca_index(two.children(arena)[0]);
+        assert_eq!(index.depth(two), None);
+        assert_eq!(index.lca(two, two), None);
+
+        let index = arena.lca_index(four);
+        assert_eq!(index.depth(eight), Some(2));
+        assert_eq!(index.lca(eight, four), Some(four));
+        assert_eq!(index.lca(eight, two), None);
+        assert_eq!(index.distance(seven, eight), Some(1));
+
+        // The tour is built without recursion.
+        let top = fixtures::chain(arena, 10_000);
+        let bottom = fixtures::find(arena, 9_999);
+        assert_eq!(arena.lca_index(top).lca(bottom, top), Some(top));
+    }
+}
@@ -1,7 +1,79 @@
-#[derive(Debug, Eq, PartialEq)]
-pub struct Arena<T> {
-    nodes: Vec<Node<T>>,
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+#[cfg(feature = "allocator-api2")]
+mod allocator;
+mod chunks;
+mod collections;
+#[cfg(feature = "std")]
+mod concurrent;
+mod cursor;
+mod diff;
+mod events;
+#[cfg(test)]
+mod fixtures;
+mod frozen;
+mod hashcons;
+mod intervals;
+mod journal;
+mod lca;
+mod map;
+mod node_map;
+mod outline;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod preorder;
+mod rewrite;
+mod scan;
+mod select;
+mod sexpr;
+mod snapshot;
+mod soa;
+mod stats;
+mod subtree;
+mod tree;
+mod visit;
+
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentArena;
+pub use cursor::Cursor;
+pub use diff::{diff, patch, Edit};
+pub use events::{ArenaEvent, Subscription};
+pub use frozen::FrozenTree;
+pub use hashcons::HashConsArena;
+pub use intervals::IntervalIndex;
+pub use lca::LcaIndex;
+pub use node_map::{NodeMap, NodeMapEntry, SparseNodeMap, SparseNodeMapEntry};
+pub use outline::ParseOutlineError;
+pub use rewrite::{Pattern, RewriteError, RewriteStep, Rewriter, Rewritten};
+pub use scan::ParseError;
+pub use select::{Match, Selector};
+pub use snapshot::ArenaSnapshot;
+pub use soa::SoaArena;
+pub use stats::ArenaStats;
+pub use tree::{Tree, TreeMut};
+pub use visit::{Control, Visitor};
+
+use chunks::Chunks;
+use events::Listeners;
+use journal::{Journal, Op};
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+use allocator_api2::vec::Vec as AllocVec;
+use core::fmt;
+use core::mem;
+use subtree::Digests;
+
+pub struct Arena<T, A: Allocator = Global> {
+    nodes: Chunks<Node<T, A>, A>,
     root: Option<NodeId>,
+    digests: Option<Digests<T>>,
+    sizes: Option<Vec<usize>>,
+    journal: Option<Journal<T>>,
+    listeners: Listeners,
 }
 
 impl<T> Arena<T> {
@@ -9,12 +81,34 @@ impl<T> Arena<T> {
     pub fn new() -> Self {
         Self::default()
     }
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Creates a new empty `Arena` allocating its nodes from `alloc`.
+    pub(crate) fn empty_in(alloc: A) -> Self {
+        Self {
+            nodes: Chunks::new_in(alloc),
+            root: None,
+            digests: None,
+            sizes: None,
+            journal: None,
+            listeners: Listeners::default(),
+        }
+    }
 
     /// Returns the number of nodes currently allocated in the `Arena` instance.
     pub fn len(&self) -> usize {
         self.nodes.len()
     }
 
+    /// Returns `true` if no nodes have been allocated in the `Arena` yet.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
     /// Returns the root `NodeId` index of the `Arena`.
     pub fn root(&self) -> Option<NodeId> {
         self.root
@@ -25,30 +119,32 @@ impl<T> Arena<T> {
     where
         OptionalId: Into<Option<NodeId>>,
     {
-        self.root = root.into()
+        let old = mem::replace(&mut self.root, root.into());
+        self.record([Op::SetRoot { root: old }]);
+        self.emit(ArenaEvent::RootChanged { old, new: self.root });
     }
 
     /// Returns a reference to a `Node` at the given `NodeId` index.
-    fn get(&self, id: NodeId) -> &Node<T> {
+    fn get(&self, id: NodeId) -> &Node<T, A> {
         &self.nodes[id.index()]
     }
 
     /// Returns a mutable reference to a `Node` at the given `NodeId` index.
-    fn get_mut(&mut self, id: NodeId) -> &mut Node<T> {
+    fn get_mut(&mut self, id: NodeId) -> &mut Node<T, A> {
         &mut self.nodes[id.index()]
     }
 
     /// Retrieves an optional reference to a `Node` at the given `NodeId` index.
-    pub fn node_at(&self, id: NodeId) -> Option<&Node<T>> {
+    pub fn node_at(&self, id: NodeId) -> Option<&Node<T, A>> {
         self.nodes.get(id.index())
     }
 
     /// Returns a new vector of all the nodes present in the `Arena`.
-    pub fn nodes(&self) -> Vec<Node<T>>
+    pub fn nodes(&self) -> Vec<Node<T, A>>
     where
         T: Clone,
     {
-        self.nodes.clone()
+        self.nodes.iter().cloned().collect()
     }
 
     /// Inserts a new value into the arena and returns its `NodeId` index.
@@ -57,30 +153,200 @@ impl<T> Arena<T> {
     /// If this is the first time a value is inserted into the `Arena`, its
     /// index will be set as the new root.
     pub fn insert(&mut self, data: T) -> NodeId {
+        if !self.is_empty() {
+            return TreeMut::push(self, data);
+        }
+
+        // Set new node as root if this is our first time inserting, undoing
+        // both in a single step.
+        let id = self.push_node(data);
+        let old = self.root.replace(id);
+        self.record([Op::Pop, Op::SetRoot { root: old }]);
+        self.emit(ArenaEvent::NodeInserted { id });
+        self.emit(ArenaEvent::RootChanged { old, new: self.root });
+        id
+    }
+
+    /// Appends a new node, without recording or reporting it.
+    fn push_node(&mut self, data: T) -> NodeId {
         let index = self.nodes.len();
-        self.nodes.push(Node::new(index, data));
+        if let Some(digests) = &mut self.digests {
+            digests.push(&data);
+        }
+        if let Some(sizes) = &mut self.sizes {
+            sizes.push(1);
+        }
+        let children = AllocVec::new_in(self.nodes.allocator().clone());
+        self.nodes.push(Node { id: NodeId(index), data, parent: None, children });
+        NodeId(index)
+    }
 
-        // Set new node as root if this is our first time inserting
-        if index == 0 {
-            self.root = Some(NodeId(index));
+    /// Inserts `child` into the children of `parent` at `index`, and sets
+    /// `parent` as its parent.
+    fn attach(&mut self, parent: NodeId, index: usize, child: NodeId) {
+        self.get_mut(parent).insert_child(index, child);
+        let old = self.get_mut(child).parent.replace(parent);
+        self.refresh_caches(parent);
+        self.record([
+            Op::RemoveChild { parent, index },
+            Op::SetParent { id: child, parent: old },
+        ]);
+        self.emit(ArenaEvent::ChildAdded { parent, index, child });
+        self.emit(ArenaEvent::ParentChanged { id: child, old, new: Some(parent) });
+    }
+
+    /// Removes `child` from the children of its parent, if it has one.
+    fn detach(&mut self, child: NodeId) {
+        if let Some(parent) = self.get(child).parent() {
+            let index = self.get_mut(parent).remove_child(child);
+            self.get_mut(child).set_parent(None);
+            self.refresh_caches(parent);
+
+            if let Some(index) = index {
+                self.record([
+                    Op::InsertChild { parent, index, child },
+                    Op::SetParent { id: child, parent: Some(parent) },
+                ]);
+                self.emit(ArenaEvent::ChildRemoved { parent, index, child });
+            } else {
+                self.record([Op::SetParent { id: child, parent: Some(parent) }]);
+            }
+            self.emit(ArenaEvent::ParentChanged { id: child, old: Some(parent), new: None });
         }
+    }
 
-        NodeId(index)
+    /// Replaces the data of the node at `id`, returning the previous data.
+    pub(crate) fn replace_data(&mut self, id: NodeId, data: T) -> T {
+        let old = mem::replace(&mut self.get_mut(id).data, data);
+        self.refresh_caches(id);
+
+        if let Some(journal) = &self.journal {
+            let data = (journal.clone)(&old);
+            self.record([Op::SetData { id, data }]);
+        }
+        self.emit(ArenaEvent::DataChanged { id });
+
+        old
+    }
+
+    /// Brings any cached digests and sizes of the node at `id` and its
+    /// ancestors up to date after the node has changed.
+    pub(crate) fn refresh_caches(&mut self, id: NodeId) {
+        self.refresh_digests(id);
+        self.refresh_sizes(id);
     }
 }
 
 impl<T> Default for Arena<T> {
     fn default() -> Self {
-        Self { nodes: Vec::new(), root: None }
+        Self::empty_in(Global)
+    }
+}
+
+impl<T, A> Tree for Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    type Data = T;
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    fn data(&self, id: NodeId) -> Option<&T> {
+        self.node_at(id).map(Node::data)
+    }
+
+    fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).parent()
+    }
+
+    fn children(&self, id: NodeId) -> &[NodeId] {
+        self.get(id).children()
+    }
+}
+
+impl<T, A> TreeMut for Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    fn push(&mut self, data: T) -> NodeId {
+        let id = self.push_node(data);
+        self.record([Op::Pop]);
+        self.emit(ArenaEvent::NodeInserted { id });
+        id
+    }
+
+    fn set_root(&mut self, root: Option<NodeId>) {
+        Arena::set_root(self, root);
+    }
+
+    fn set_data(&mut self, id: NodeId, data: T) -> T {
+        self.replace_data(id, data)
+    }
+
+    fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let old = mem::replace(&mut self.get_mut(id).parent, parent);
+        self.record([Op::SetParent { id, parent: old }]);
+        self.emit(ArenaEvent::ParentChanged { id, old, new: parent });
+    }
+
+    fn insert_child(&mut self, parent: NodeId, index: usize, child: NodeId) {
+        self.attach(parent, index, child);
+    }
+
+    fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Option<usize> {
+        let index = self.get_mut(parent).remove_child(child)?;
+        self.refresh_caches(parent);
+        self.record([Op::InsertChild { parent, index, child }]);
+        self.emit(ArenaEvent::ChildRemoved { parent, index, child });
+        Some(index)
+    }
+
+    fn insert(&mut self, data: T) -> NodeId {
+        Arena::insert(self, data)
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        Arena::detach(self, id);
+    }
+}
+
+impl<T, A> fmt::Debug for Arena<T, A>
+where
+    T: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("nodes", &self.nodes)
+            .field("root", &self.root)
+            .finish()
+    }
+}
+
+impl<T, A> PartialEq for Arena<T, A>
+where
+    T: PartialEq,
+    A: Allocator + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.nodes == other.nodes && self.root == other.root
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Node<T> {
+impl<T, A> Eq for Arena<T, A> where T: Eq, A: Allocator + Clone {}
+
+#[derive(Clone)]
+pub struct Node<T, A: Allocator = Global> {
     id: NodeId,
     data: T,
     parent: Option<NodeId>,
-    children: Vec<NodeId>,
+    children: AllocVec<NodeId, A>,
 }
 
 impl<T> Node<T> {
@@ -106,7 +372,12 @@ impl<T> Node<T> {
             children: children.into_iter().map(Id::into).collect()
         }
     }
+}
 
+impl<T, A> Node<T, A>
+where
+    A: Allocator,
+{
     /// Returns the `NodeId` identifier of the node.
     ///
     /// This value is used to uniquely identify a node in an `Arena`. It holds
@@ -136,16 +407,65 @@ impl<T> Node<T> {
         self.parent = new_parent.into()
     }
 
-    pub fn children(&self) -> &Vec<NodeId> {
+    pub fn children(&self) -> &[NodeId] {
         &self.children
     }
 
     pub fn add_child(&mut self, child: NodeId) {
         self.children.push(child)
     }
+
+    /// Inserts a child at the given position in this node's children list,
+    /// shifting all children after it to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of children.
+    pub fn insert_child(&mut self, index: usize, child: NodeId) {
+        self.children.insert(index, child)
+    }
+
+    /// Removes a child from this node's children list.
+    ///
+    /// Returns the position the child was found at, if it was present.
+    pub fn remove_child(&mut self, child: NodeId) -> Option<usize> {
+        let index = self.children.iter().position(|&id| id == child)?;
+        self.children.remove(index);
+        Some(index)
+    }
+}
+
+impl<T, A> fmt::Debug for Node<T, A>
+where
+    T: fmt::Debug,
+    A: Allocator,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("id", &self.id)
+            .field("data", &self.data)
+            .field("parent", &self.parent)
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+impl<T, A> PartialEq for Node<T, A>
+where
+    T: PartialEq,
+    A: Allocator,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.data == other.data
+            && self.parent == other.parent
+            && self.children == other.children
+    }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+impl<T, A> Eq for Node<T, A> where T: Eq, A: Allocator {}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct NodeId(usize);
 
 impl NodeId {
@@ -155,19 +475,19 @@ impl NodeId {
     }
 
     /// Retrieves the parent of the `Node` with this `NodeId`.
-    pub fn parent<T>(&self, arena: &Arena<T>) -> Option<NodeId>
+    pub fn parent<A>(&self, tree: &A) -> Option<NodeId>
     where
-        T: PartialEq,
+        A: Tree,
     {
-        arena.get(*self).parent()
+        tree.parent(*self)
     }
 
     /// Retrieves a reference to the children of the `Node` with this `NodeId`.
-    pub fn children<'a, T>(&self, arena: &'a Arena<T>) -> &'a Vec<NodeId>
+    pub fn children<'a, A>(&self, tree: &'a A) -> &'a [NodeId]
     where
-        T: PartialEq,
+        A: Tree,
     {
-        arena.get(*self).children()
+        tree.children(*self)
     }
 
     /// Sets a new parent for this `Node`.
@@ -198,12 +518,12 @@ impl NodeId {
     /// root_node.add_child(arena, second_node);
     /// assert_eq!(root_node.children(arena), &vec![NodeId::from(1)]);
     /// ```
-    pub fn set_parent<T, P>(&self, arena: &mut Arena<T>, new_parent: P) -> &Self
+    pub fn set_parent<A, P>(&self, tree: &mut A, new_parent: P) -> &Self
     where
-        T: PartialEq,
+        A: TreeMut,
         P: Into<Option<NodeId>>,
     {
-        arena.get_mut(*self).set_parent(new_parent);
+        tree.set_parent(*self, new_parent.into());
         self
     }
 
@@ -249,12 +569,81 @@ impl NodeId {
     ///     NodeId::from(2),
     /// ]);
     /// ```
-    pub fn add_child<T>(&self, arena: &mut Arena<T>, child: NodeId) -> &Self
+    pub fn add_child<A>(&self, tree: &mut A, child: NodeId) -> &Self
+    where
+        A: TreeMut,
+    {
+        tree.add_child(*self, child);
+        self
+    }
+
+    /// Inserts a new child at the given position in this `Node`'s children.
+    ///
+    /// This behaves like `NodeId::add_child`, except that the child is placed
+    /// at `index` instead of being appended to the end of the children list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of children.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use crate::isaac::{Arena, NodeId};
+    /// let arena = &mut Arena::new();
+    /// let root_node = arena.insert("1");
+    /// let second_node = arena.insert("2");
+    /// let third_node = arena.insert("3");
+    ///
+    /// root_node
+    ///     .add_child(arena, third_node)
+    ///     .insert_child(arena, 0, second_node);
+    ///
+    /// assert_eq!(root_node.children(arena), &vec![second_node, third_node]);
+    /// assert_eq!(second_node.parent(arena), Some(root_node));
+    /// ```
+    pub fn insert_child<A>(&self, tree: &mut A, index: usize, child: NodeId) -> &Self
+    where
+        A: TreeMut,
+    {
+        tree.insert_child(*self, index, child);
+        self
+    }
+
+    /// Detaches this `Node` from its parent.
+    ///
+    /// The node is removed from its parent's children list and its parent is
+    /// cleared. The node and its descendants remain in the arena, and can be
+    /// attached elsewhere with `NodeId::add_child`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use crate::isaac::{Arena, NodeId};
+    /// let arena = &mut Arena::new();
+    /// let root_node = arena.insert("1");
+    /// let second_node = arena.insert("2");
+    ///
+    /// root_node.add_child(arena, second_node);
+    /// second_node.detach(arena);
+    ///
+    /// assert_eq!(root_node.children(arena), &vec![]);
+    /// assert_eq!(second_node.parent(arena), None);
+    /// ```
+    pub fn detach<A>(&self, tree: &mut A) -> &Self
+    where
+        A: TreeMut,
+    {
+        tree.detach(*self);
+        self
+    }
+
+    /// Replaces the data contained in this `Node`.
+    pub fn set_data<A>(&self, tree: &mut A, data: A::Data) -> &Self
     where
-        T: PartialEq,
+        A: TreeMut,
     {
-        arena.get_mut(*self).add_child(child);
-        arena.get_mut(child).set_parent(*self);
+        tree.set_data(*self, data);
         self
     }
 }
@@ -296,28 +685,29 @@ mod tests {
                     id: NodeId(0),
                     data: "1",
                     parent: None,
-                    children: vec![NodeId(1), NodeId(2)],
+                    children: allocator_api2::vec![NodeId(1), NodeId(2)],
                 },
                 Node {
                     id: NodeId(1),
                     data: "2",
                     parent: Some(NodeId(0)),
-                    children: vec![],
+                    children: allocator_api2::vec![],
                 },
                 Node {
                     id: NodeId(2),
                     data: "3",
                     parent: Some(NodeId(0)),
-                    children: vec![NodeId(3)],
+                    children: allocator_api2::vec![NodeId(3)],
                 },
                 Node {
                     id: NodeId(3),
                     data: "4",
                     parent: Some(NodeId(2)),
-                    children: vec![],
+                    children: allocator_api2::vec![],
                 },
-            ],
+            ].into(),
             root: Some(NodeId(0)),
+            ..Arena::new()
         });
     }
 
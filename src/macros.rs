@@ -0,0 +1,134 @@
+/// Inserts a tree of values into an `Arena` and returns the `NodeId` of its
+/// root.
+///
+/// The first argument is a `&mut Arena<T>`. It is followed by the root of the
+/// tree, written as a value optionally followed by `=>` and a bracketed list
+/// of children, each of which can have children of its own. Values can be
+/// any expression evaluating to `T`.
+///
+/// Nodes are inserted in pre-order and linked with `NodeId::add_child`, so
+/// the resulting arena is the same as one built by hand in that order.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena};
+/// //     1
+/// //    / \
+/// //   2   3
+/// //       |
+/// //       4
+/// let arena = &mut Arena::new();
+/// let str_1 = tree!(arena, "1" => ["2", "3" => ["4"]]);
+///
+/// let str_2 = str_1.children(arena)[0];
+/// let str_3 = str_1.children(arena)[1];
+/// let str_4 = str_3.children(arena)[0];
+///
+/// assert_eq!(arena.root(), Some(str_1));
+/// assert_eq!(str_2.parent(arena), Some(str_1));
+/// assert_eq!(str_4.parent(arena), Some(str_3));
+/// assert_eq!(arena.node_at(str_4).unwrap().data(), &"4");
+/// ```
+#[macro_export]
+macro_rules! tree {
+    // Each list of children is expanded at once, so the macro only recurses
+    // once per level of nesting, however many siblings there are.
+    (@node $arena:ident, $data:expr => [
+        $($child:expr $(=> [$($children:tt)*])?),* $(,)?
+    ]) => {{
+        let parent = $arena.insert($data);
+        $(
+            let child = $crate::tree!(@node $arena, $child $(=> [$($children)*])?);
+            parent.add_child($arena, child);
+        )*
+        parent
+    }};
+    (@node $arena:ident, $data:expr) => {{
+        $arena.insert($data)
+    }};
+    ($arena:expr, $($tree:tt)+) => {{
+        let arena: &mut $crate::Arena<_> = $arena;
+        $crate::tree!(@node arena, $($tree)+)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Arena;
+
+    #[test]
+    fn test_tree_macro() {
+        //     1
+        //    / \
+        //   2   3
+        //       |
+        //       4
+        let arena = &mut Arena::new();
+        let str_1 = tree!(arena, "1" => ["2", "3" => ["4",],]);
+
+        assert_eq!(*arena, {
+            let arena = &mut Arena::new();
+
+            let str_1 = arena.insert("1");
+            let str_2 = arena.insert("2");
+            let str_3 = arena.insert("3");
+            let str_4 = arena.insert("4");
+
+            str_1
+                .add_child(arena, str_2)
+                .add_child(arena, str_3);
+
+            str_3
+                .add_child(arena, str_4);
+
+            assert_eq!(str_1, arena.root().unwrap());
+            std::mem::take(arena)
+        });
+        assert_eq!(arena.root(), Some(str_1));
+    }
+
+    #[test]
+    fn test_tree_macro_expressions() {
+        let mut arena = Arena::new();
+        let base = 10;
+
+        let root = tree!(&mut arena, base * 2 => [
+            [1, 2].iter().sum::<i32>() => [base],
+            { let x = 3; x + base } => [],
+        ]);
+        let leaf = tree!(&mut arena, -1);
+
+        let data = |id| *arena.node_at(id).unwrap().data();
+        let children = root.children(&arena);
+
+        assert_eq!(data(root), 20);
+        assert_eq!(data(children[0]), 3);
+        assert_eq!(data(children[0].children(&arena)[0]), 10);
+        assert_eq!(data(children[1]), 13);
+        assert_eq!(leaf.parent(&arena), None);
+        assert_eq!(arena.root(), Some(root));
+    }
+
+    #[test]
+    fn test_tree_macro_wide() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, -1 => [
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+            20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39,
+            40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+            60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79,
+            80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 96, 97, 98, 99,
+            100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117, 118, 119,
+            120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 139,
+            140, 141, 142, 143, 144, 145, 146, 147, 148, 149, 150, 151, 152, 153, 154, 155, 156, 157, 158, 159,
+            160, 161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 176, 177, 178, 179,
+            180, 181, 182, 183, 184, 185, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196, 197, 198, 199,
+        ]);
+
+        // More siblings than the default recursion limit of 128.
+        let children = root.children(arena);
+        assert_eq!(children.len(), 200);
+        assert!(children.iter().enumerate().all(|(i, &id)| *arena.node_at(id).unwrap().data() == i as i32));
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{Arena, Node, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Creates a new `Arena` with the same shape as this one, where the data
+    /// of every node is replaced with the result of calling `f` on it.
+    ///
+    /// Every node keeps its `NodeId`, parent and children, and the root is
+    /// left unchanged. Cached subtree sizes are carried over, but digests are
+    /// not, as `U` may not be hashable. The new arena allocates from the
+    /// same allocator as this one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["22", "333" => ["4444"]]);
+    ///
+    /// let lengths = arena.map(|_, data| data.len());
+    /// assert_eq!(lengths.to_sexpr(root), "(1 (2) (3 (4)))");
+    /// ```
+    pub fn map<U, F>(&self, mut f: F) -> Arena<U, A>
+    where
+        F: FnMut(NodeId, &T) -> U,
+    {
+        let mut arena = Arena::empty_in(self.nodes.allocator().clone());
+        arena.nodes.extend(self.nodes.iter().map(|node| Node {
+            id: node.id,
+            data: f(node.id, &node.data),
+            parent: node.parent,
+            children: node.children.clone(),
+        }));
+
+        Arena {
+            root: self.root,
+            sizes: self.sizes.clone(),
+            ..arena
+        }
+    }
+
+    /// Consumes this `Arena`, creating a new one with the same shape where
+    /// the data of every node is replaced with the result of calling `f` on
+    /// it.
+    ///
+    /// This behaves like `Arena::map`, except that `f` takes ownership of the
+    /// data of each node.
+    pub fn into_map<U, F>(self, mut f: F) -> Arena<U, A>
+    where
+        F: FnMut(NodeId, T) -> U,
+    {
+        let mut arena = Arena::empty_in(self.nodes.allocator().clone());
+        arena.nodes.extend(self.nodes.into_iter().map(|node| Node {
+            id: node.id,
+            data: f(node.id, node.data),
+            parent: node.parent,
+            children: node.children,
+        }));
+
+        Arena {
+            root: self.root,
+            sizes: self.sizes,
+            ..arena
+        }
+    }
+
+    /// Creates a new `Arena` from the subtree at `root`, keeping only the
+    /// nodes for which `f` returns `Some`.
+    ///
+    /// The children of a dropped node are re-linked to its nearest kept
+    /// ancestor, in the position of the dropped node. Kept nodes are inserted
+    /// in pre-order, so they are given new `NodeId`s, and the first of them
+    /// becomes the root of the new arena. If `root` itself is dropped, its
+    /// kept descendants without a kept ancestor are left without a parent.
+    /// The new arena allocates from the same allocator as this one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "+" => ["1", "()" => ["*" => ["2", "()" => ["3"]]]]);
+    ///
+    /// // Drop every group, and convert the rest to owned strings.
+    /// let simplified = arena.filter_map_subtree(root, |_, data| {
+    ///     if *data == "()" { None } else { Some(data.to_string()) }
+    /// });
+    ///
+    /// let root = simplified.root().unwrap();
+    /// assert_eq!(simplified.to_sexpr(root), "(+ (1) (* (2) (3)))");
+    /// ```
+    pub fn filter_map_subtree<U, F>(&self, root: NodeId, mut f: F) -> Arena<U, A>
+    where
+        F: FnMut(NodeId, &T) -> Option<U>,
+    {
+        let mut arena = Arena::empty_in(self.nodes.allocator().clone());
+        let mut stack: Vec<(NodeId, Option<NodeId>)> = vec![(root, None)];
+
+        while let Some((id, ancestor)) = stack.pop() {
+            let node = self.get(id);
+            let ancestor = match f(id, node.data()) {
+                Some(data) => {
+                    let kept = arena.insert(data);
+                    if let Some(ancestor) = ancestor {
+                        ancestor.add_child(&mut arena, kept);
+                    }
+                    Some(kept)
+                },
+                None => ancestor,
+            };
+
+            stack.extend(node.children().iter().rev().map(|&child| (child, ancestor)));
+        }
+
+        arena
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_map() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+        let orphan = arena.insert("5");
+        arena.enable_subtree_sizes();
+
+        let mapped = arena.map(|id, data| format!("{}:{}", id.index(), data));
+        assert_eq!(mapped.root(), Some(root));
+        assert_eq!(mapped.to_sexpr(root), "(0:1 (1:2) (2:3 (3:4)))");
+        assert_eq!(mapped.subtree_size(root), 4);
+        assert!(mapped.has_subtree_sizes());
+        assert!(!mapped.has_digests());
+
+        let numbers = mapped.into_map(|_, data| data.len());
+        assert_eq!(numbers.to_sexpr(root), "(3 (3) (3 (3)))");
+        assert_eq!(numbers.node_at(orphan).unwrap().parent(), None);
+    }
+
+    #[test]
+    fn test_filter_map_subtree() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let even = |_, &data: &i32| if data % 2 == 0 { Some(data) } else { None };
+
+        // Dropping the root leaves every top-level kept node without a parent.
+        let kept = arena.filter_map_subtree(root, even);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept.to_sexpr(kept.root().unwrap()), "(2 (6))");
+        assert_eq!(kept.node_at(NodeId(2)).unwrap().data(), &4);
+        assert_eq!(NodeId(2).parent(&kept), None);
+        assert_eq!(kept.to_sexpr(NodeId(2)), "(4 (8))");
+
+        // Children of dropped nodes take their place under the kept ancestor.
+        let kept = arena.filter_map_subtree(root, |_, &data| if data == 2 || data == 4 { None } else { Some(data) });
+        assert_eq!(kept.to_sexpr(kept.root().unwrap()), "(1 (5) (6) (3) (7 (8)))");
+    }
+
+    #[test]
+    fn test_filter_map_subtree_edge_cases() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+
+        // Dropping only the root splits its children into separate trees,
+        // and the first of them becomes the new root.
+        let kept = arena.filter_map_subtree(root, |id, &data| if id == root { None } else { Some(data) });
+        let tops: Vec<NodeId> = (0..kept.len()).map(NodeId).filter(|&id| id.parent(&kept).is_none()).collect();
+        assert_eq!(kept.len(), 7);
+        assert_eq!(kept.root(), Some(tops[0]));
+        let trees: Vec<String> = tops.iter().map(|&id| kept.to_sexpr(id)).collect();
+        assert_eq!(trees, ["(2 (5) (6))", "(3)", "(4 (7 (8)))"]);
+
+        // Dropping every node leaves an empty arena without a root.
+        let none = arena.filter_map_subtree(root, |_, _| None::<i32>);
+        assert!(none.is_empty());
+        assert_eq!(none.root(), None);
+
+        // Only the subtree is visited, with the original `NodeId`s.
+        let four = fixtures::find(arena, 4);
+        let mut visited = vec![];
+        arena.filter_map_subtree(four, |id, _| {
+            visited.push(id);
+            Some(())
+        });
+        assert_eq!(visited, arena.preorder(four).map(|(id, _)| id).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,464 @@
+use crate::collections::{map, Map};
+use crate::{Arena, NodeId};
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::iter::FromIterator;
+use core::ops::{Index, IndexMut};
+
+/// A map from `NodeId`s to values, for attaching side data to the nodes of
+/// an `Arena` without storing it in the nodes themselves.
+///
+/// Values are stored in a vector indexed by `NodeId`, which makes lookups as
+/// cheap as accessing the arena itself. The map grows as needed, and can be
+/// sized up front with `NodeMap::for_arena`. Use `SparseNodeMap` when only a
+/// small fraction of the nodes have a value.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena, NodeMap};
+/// let arena = &mut Arena::new();
+/// let root = tree!(arena, "+" => ["1", "2"]);
+///
+/// // Attach the value of every literal to its node.
+/// let mut values = NodeMap::for_arena(arena);
+/// for &child in root.children(arena) {
+///     values.insert(child, arena.node_at(child).unwrap().data().parse::<i32>().unwrap());
+/// }
+///
+/// let sum: i32 = values.values().sum();
+/// *values.entry(root).or_insert(0) += sum;
+///
+/// assert_eq!(values[root], 3);
+/// assert_eq!(values.len(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NodeMap<V> {
+    values: Vec<Option<V>>,
+    len: usize,
+}
+
+impl<V> NodeMap<V> {
+    /// Creates a new empty `NodeMap`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new empty `NodeMap` with room for a value for every node
+    /// currently in `arena`.
+    pub fn for_arena<T, A>(arena: &Arena<T, A>) -> Self
+    where
+        A: Allocator + Clone,
+    {
+        let mut values = Vec::with_capacity(arena.len());
+        values.resize_with(arena.len(), || None);
+        Self { values, len: 0 }
+    }
+
+    /// Returns the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the map holds a value for `id`.
+    pub fn contains_key(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns a reference to the value for `id`, if there is one.
+    pub fn get(&self, id: NodeId) -> Option<&V> {
+        self.values.get(id.index())?.as_ref()
+    }
+
+    /// Returns a mutable reference to the value for `id`, if there is one.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut V> {
+        self.values.get_mut(id.index())?.as_mut()
+    }
+
+    /// Inserts a value for `id`, returning the previous value if there was
+    /// one.
+    pub fn insert(&mut self, id: NodeId, value: V) -> Option<V> {
+        let previous = self.slot(id).replace(value);
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Removes the value for `id`, returning it if there was one.
+    pub fn remove(&mut self, id: NodeId) -> Option<V> {
+        let previous = self.values.get_mut(id.index())?.take();
+        if previous.is_some() {
+            self.len -= 1;
+        }
+        previous
+    }
+
+    /// Returns the entry for `id`, for in-place manipulation.
+    pub fn entry(&mut self, id: NodeId) -> NodeMapEntry<'_, V> {
+        self.slot(id);
+        NodeMapEntry { slot: &mut self.values[id.index()], len: &mut self.len }
+    }
+
+    /// Removes every value from the map.
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.len = 0;
+    }
+
+    /// Returns an iterator over the `NodeId`s and values in the map, in
+    /// order of their `NodeId`s.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &V)> {
+        self.values.iter()
+            .enumerate()
+            .filter_map(|(index, value)| Some((NodeId(index), value.as_ref()?)))
+    }
+
+    /// Returns an iterator over the `NodeId`s and mutable references to the
+    /// values in the map, in order of their `NodeId`s.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (NodeId, &mut V)> {
+        self.values.iter_mut()
+            .enumerate()
+            .filter_map(|(index, value)| Some((NodeId(index), value.as_mut()?)))
+    }
+
+    /// Returns an iterator over the `NodeId`s in the map, in order.
+    pub fn keys(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter().map(|(id, _)| id)
+    }
+
+    /// Returns an iterator over the values in the map, in order of their
+    /// `NodeId`s.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.values.iter().flatten()
+    }
+
+    /// Returns the slot for `id`, growing the map if needed.
+    fn slot(&mut self, id: NodeId) -> &mut Option<V> {
+        if id.index() >= self.values.len() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        &mut self.values[id.index()]
+    }
+}
+
+impl<V> Default for NodeMap<V> {
+    fn default() -> Self {
+        Self { values: Vec::new(), len: 0 }
+    }
+}
+
+impl<V> PartialEq for NodeMap<V>
+where
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<V> Eq for NodeMap<V> where V: Eq {}
+
+impl<V> Index<NodeId> for NodeMap<V> {
+    type Output = V;
+
+    fn index(&self, id: NodeId) -> &V {
+        self.get(id).expect("node should have a value in the map")
+    }
+}
+
+impl<V> IndexMut<NodeId> for NodeMap<V> {
+    fn index_mut(&mut self, id: NodeId) -> &mut V {
+        self.get_mut(id).expect("node should have a value in the map")
+    }
+}
+
+impl<V> Extend<(NodeId, V)> for NodeMap<V> {
+    fn extend<I: IntoIterator<Item = (NodeId, V)>>(&mut self, iter: I) {
+        for (id, value) in iter {
+            self.insert(id, value);
+        }
+    }
+}
+
+impl<V> FromIterator<(NodeId, V)> for NodeMap<V> {
+    fn from_iter<I: IntoIterator<Item = (NodeId, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// An entry of a `NodeMap`, which may or may not hold a value.
+pub struct NodeMapEntry<'a, V> {
+    slot: &'a mut Option<V>,
+    len: &'a mut usize,
+}
+
+impl<'a, V> NodeMapEntry<'a, V> {
+    /// Inserts `default` if the entry has no value, and returns a mutable
+    /// reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the entry has no value, and returns
+    /// a mutable reference to the value.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        if self.slot.is_none() {
+            *self.len += 1;
+        }
+        self.slot.get_or_insert_with(default)
+    }
+
+    /// Inserts the default value if the entry has no value, and returns a
+    /// mutable reference to the value.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Calls `f` with the value of the entry, if it has one.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Some(value) = self.slot.as_mut() {
+            f(value);
+        }
+        self
+    }
+}
+
+/// A map from `NodeId`s to values, for side data that only a small fraction
+/// of the nodes of an `Arena` have.
+///
+/// This is a thin wrapper around a `HashMap`, or a `BTreeMap` without the
+/// `std` feature, mirroring the API of `NodeMap`. Iteration order is
+/// unspecified.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SparseNodeMap<V> {
+    values: Map<NodeId, V>,
+}
+
+impl<V> SparseNodeMap<V> {
+    /// Creates a new empty `SparseNodeMap`.
+    pub fn new() -> Self {
+        Self { values: Map::new() }
+    }
+
+    /// Returns the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the map holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns `true` if the map holds a value for `id`.
+    pub fn contains_key(&self, id: NodeId) -> bool {
+        self.values.contains_key(&id)
+    }
+
+    /// Returns a reference to the value for `id`, if there is one.
+    pub fn get(&self, id: NodeId) -> Option<&V> {
+        self.values.get(&id)
+    }
+
+    /// Returns a mutable reference to the value for `id`, if there is one.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut V> {
+        self.values.get_mut(&id)
+    }
+
+    /// Inserts a value for `id`, returning the previous value if there was
+    /// one.
+    pub fn insert(&mut self, id: NodeId, value: V) -> Option<V> {
+        self.values.insert(id, value)
+    }
+
+    /// Removes the value for `id`, returning it if there was one.
+    pub fn remove(&mut self, id: NodeId) -> Option<V> {
+        self.values.remove(&id)
+    }
+
+    /// Returns the entry for `id`, for in-place manipulation.
+    pub fn entry(&mut self, id: NodeId) -> SparseNodeMapEntry<'_, V> {
+        SparseNodeMapEntry { entry: self.values.entry(id) }
+    }
+
+    /// Removes every value from the map.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Returns an iterator over the `NodeId`s and values in the map.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &V)> {
+        self.values.iter().map(|(&id, value)| (id, value))
+    }
+
+    /// Returns an iterator over the `NodeId`s and mutable references to the
+    /// values in the map.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (NodeId, &mut V)> {
+        self.values.iter_mut().map(|(&id, value)| (id, value))
+    }
+
+    /// Returns an iterator over the `NodeId`s in the map.
+    pub fn keys(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.values.keys().copied()
+    }
+
+    /// Returns an iterator over the values in the map.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.values.values()
+    }
+}
+
+impl<V> Index<NodeId> for SparseNodeMap<V> {
+    type Output = V;
+
+    fn index(&self, id: NodeId) -> &V {
+        self.get(id).expect("node should have a value in the map")
+    }
+}
+
+impl<V> IndexMut<NodeId> for SparseNodeMap<V> {
+    fn index_mut(&mut self, id: NodeId) -> &mut V {
+        self.get_mut(id).expect("node should have a value in the map")
+    }
+}
+
+impl<V> Extend<(NodeId, V)> for SparseNodeMap<V> {
+    fn extend<I: IntoIterator<Item = (NodeId, V)>>(&mut self, iter: I) {
+        self.values.extend(iter);
+    }
+}
+
+impl<V> FromIterator<(NodeId, V)> for SparseNodeMap<V> {
+    fn from_iter<I: IntoIterator<Item = (NodeId, V)>>(iter: I) -> Self {
+        Self { values: iter.into_iter().collect() }
+    }
+}
+
+/// An entry of a `SparseNodeMap`, which may or may not hold a value.
+pub struct SparseNodeMapEntry<'a, V> {
+    entry: map::Entry<'a, NodeId, V>,
+}
+
+impl<'a, V> SparseNodeMapEntry<'a, V> {
+    /// Inserts `default` if the entry has no value, and returns a mutable
+    /// reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.entry.or_insert(default)
+    }
+
+    /// Inserts the result of `default` if the entry has no value, and returns
+    /// a mutable reference to the value.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.entry.or_insert_with(default)
+    }
+
+    /// Inserts the default value if the entry has no value, and returns a
+    /// mutable reference to the value.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.entry.or_default()
+    }
+
+    /// Calls `f` with the value of the entry, if it has one.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        Self { entry: self.entry.and_modify(f) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_map() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+        let depths: NodeMap<usize> = arena.preorder(root).collect();
+
+        assert_eq!(depths.len(), 4);
+        assert_eq!(depths.iter().collect::<Vec<_>>(), vec![
+            (NodeId(0), &0),
+            (NodeId(1), &1),
+            (NodeId(2), &1),
+            (NodeId(3), &2),
+        ]);
+
+        let mut map = NodeMap::for_arena(arena);
+        assert!(map.is_empty());
+        assert_eq!(map.get(NodeId(100)), None);
+        assert_eq!(map.remove(NodeId(100)), None);
+
+        assert_eq!(map.insert(NodeId(3), "a"), None);
+        assert_eq!(map.insert(NodeId(3), "b"), Some("a"));
+        assert_eq!(map.insert(NodeId(10), "c"), None);
+        map.entry(NodeId(1)).or_insert("d");
+        map.entry(NodeId(1)).and_modify(|value| *value = "e").or_insert("f");
+        map[NodeId(10)] = "g";
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![NodeId(1), NodeId(3), NodeId(10)]);
+        assert_eq!(map.values().collect::<Vec<_>>(), vec![&"e", &"b", &"g"]);
+        assert!(map.contains_key(NodeId(10)));
+
+        assert_eq!(map.remove(NodeId(3)), Some("b"));
+        assert_eq!(map.len(), 2);
+        for (_, value) in map.iter_mut() {
+            *value = "h";
+        }
+        assert_eq!(map.get(NodeId(1)), Some(&"h"));
+
+        // Maps compare equal regardless of how much room they have.
+        let mut other: NodeMap<&str> = map.iter().map(|(id, &value)| (id, value)).collect();
+        other.insert(NodeId(50), "i");
+        other.remove(NodeId(50));
+        assert_eq!(map, other);
+    }
+
+    #[test]
+    fn test_sparse_node_map() {
+        let mut map = SparseNodeMap::new();
+        assert!(map.is_empty());
+
+        assert_eq!(map.insert(NodeId(1_000_000), 1), None);
+        assert_eq!(map.insert(NodeId(1_000_000), 2), Some(1));
+        *map.entry(NodeId(5)).or_insert(0) += 10;
+        map.extend(vec![(NodeId(7), 7)]);
+        map[NodeId(7)] += 1;
+        map.entry(NodeId(7)).and_modify(|value| *value *= 2).or_default();
+        *map.entry(NodeId(9)).and_modify(|value| *value = 1).or_default() += 3;
+        assert_eq!(*map.entry(NodeId(9)).or_insert_with(|| 0), 3);
+        assert_eq!(map.remove(NodeId(9)), Some(3));
+
+        let mut entries: Vec<_> = map.iter().map(|(id, &value)| (id, value)).collect();
+        entries.sort();
+        assert_eq!(entries, vec![(NodeId(5), 10), (NodeId(7), 16), (NodeId(1_000_000), 2)]);
+        assert_eq!(map.remove(NodeId(5)), Some(10));
+        assert_eq!(map.get(NodeId(5)), None);
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{Arena, NodeId};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::error::Error;
+use core::fmt::{self, Display, Write};
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Renders the subtree starting at `root` as an indented outline.
+    ///
+    /// Each node is written on its own line using `fmt`, indented by two
+    /// spaces for every level below `root`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let arena = &mut Arena::new();
+    /// let str_1 = arena.insert("1");
+    /// let str_2 = arena.insert("2");
+    /// let str_3 = arena.insert("3");
+    ///
+    /// str_1.add_child(arena, str_2);
+    /// str_2.add_child(arena, str_3);
+    ///
+    /// assert_eq!(arena.to_outline(str_1, |data| *data), "1\n  2\n    3\n");
+    /// ```
+    pub fn to_outline<F, D>(&self, root: NodeId, mut fmt: F) -> String
+    where
+        F: FnMut(&T) -> D,
+        D: Display,
+    {
+        let mut out = String::new();
+        let mut stack = vec![(root, 0)];
+
+        while let Some((id, depth)) = stack.pop() {
+            let node = self.get(id);
+            for _ in 0..depth {
+                out.push_str("  ");
+            }
+            writeln!(out, "{}", fmt(node.data()))
+                .expect("writing to a `String` cannot fail");
+
+            for &child in node.children().iter().rev() {
+                stack.push((child, depth + 1));
+            }
+        }
+
+        out
+    }
+}
+
+impl<T> Arena<T> {
+    /// Builds a new `Arena` from an indentation-based outline.
+    ///
+    /// Every non-blank line becomes a node whose data is produced by passing
+    /// the trimmed line to `parse_line`. A line becomes a child of the
+    /// closest preceding line that is indented one level less than itself.
+    /// The first line is the root of the new arena.
+    ///
+    /// Indentation may use either spaces or tabs, but not both. The width of
+    /// the first indented line sets the width of one level, and every other
+    /// line must be indented by a multiple of it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let outline = "
+    /// 1
+    ///     2
+    ///     3
+    ///         4
+    /// ";
+    ///
+    /// let arena = Arena::from_outline(outline, |line| line.parse::<u32>()).unwrap();
+    /// let root = arena.root().unwrap();
+    ///
+    /// assert_eq!(root.children(&arena).len(), 2);
+    /// assert_eq!(arena.to_outline(root, |data| *data), "1\n  2\n  3\n    4\n");
+    /// ```
+    pub fn from_outline<F, E>(text: &str, mut parse_line: F) -> Result<Self, ParseOutlineError<E>>
+    where
+        F: FnMut(&str) -> Result<T, E>,
+    {
+        let mut arena = Arena::new();
+        let mut unit: Option<(char, usize)> = None;
+        let mut stack: Vec<NodeId> = Vec::new();
+
+        for (index, raw_line) in text.lines().enumerate() {
+            let line = index + 1;
+            let content = raw_line.trim();
+
+            if content.is_empty() {
+                continue;
+            }
+
+            let indent = &raw_line[..raw_line.len() - raw_line.trim_start().len()];
+            let level = match indent.chars().next() {
+                None => 0,
+                Some(ch) => {
+                    if indent.chars().any(|other| other != ch) {
+                        return Err(ParseOutlineError::MixedIndentation { line });
+                    }
+
+                    let (unit_ch, unit_width) = *unit.get_or_insert((ch, indent.len()));
+                    if unit_ch != ch {
+                        return Err(ParseOutlineError::MixedIndentation { line });
+                    }
+                    if indent.len() % unit_width != 0 {
+                        return Err(ParseOutlineError::InconsistentIndentation { line });
+                    }
+
+                    indent.len() / unit_width
+                },
+            };
+
+            if level > stack.len() {
+                return Err(ParseOutlineError::UnexpectedIndent { line });
+            }
+            if level == 0 && !stack.is_empty() {
+                return Err(ParseOutlineError::MultipleRoots { line });
+            }
+
+            let data = parse_line(content)
+                .map_err(|error| ParseOutlineError::Line { line, error })?;
+            let id = arena.insert(data);
+
+            stack.truncate(level);
+            if let Some(&parent) = stack.last() {
+                parent.add_child(&mut arena, id);
+            }
+            stack.push(id);
+        }
+
+        Ok(arena)
+    }
+}
+
+/// An error returned when `Arena::from_outline` fails to parse its input.
+///
+/// Line numbers start from one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseOutlineError<E> {
+    /// The line is indented with both spaces and tabs.
+    MixedIndentation { line: usize },
+    /// The line is not indented by a multiple of one level.
+    InconsistentIndentation { line: usize },
+    /// The line is indented more than one level deeper than the line before.
+    UnexpectedIndent { line: usize },
+    /// The line is not indented, but the outline already has a root.
+    MultipleRoots { line: usize },
+    /// The line was rejected by the line parser.
+    Line { line: usize, error: E },
+}
+
+impl<E> Display for ParseOutlineError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedIndentation { line } => {
+                write!(f, "line {} mixes tabs and spaces in its indentation", line)
+            },
+            Self::InconsistentIndentation { line } => {
+                write!(f, "line {} is not indented by a whole number of levels", line)
+            },
+            Self::UnexpectedIndent { line } => {
+                write!(f, "line {} is indented too deeply", line)
+            },
+            Self::MultipleRoots { line } => {
+                write!(f, "line {} starts a second root", line)
+            },
+            Self::Line { line, error } => {
+                write!(f, "invalid line {}: {}", line, error)
+            },
+        }
+    }
+}
+
+impl<E> Error for ParseOutlineError<E> where E: Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Arena<String>, ParseOutlineError<()>> {
+        Arena::from_outline(text, |line| Ok(line.to_string()))
+    }
+
+    #[test]
+    fn test_outline_round_trip() {
+        let arena = parse("root\n\tleft\n\t\tleaf\n\n\tright\n").unwrap();
+        let root = arena.root().unwrap();
+        let left = root.children(&arena)[0];
+        let right = root.children(&arena)[1];
+
+        assert_eq!(left.parent(&arena), Some(root));
+        assert_eq!(right.parent(&arena), Some(root));
+        assert_eq!(left.children(&arena).len(), 1);
+
+        let outline = arena.to_outline(root, String::clone);
+        assert_eq!(outline, "root\n  left\n    leaf\n  right\n");
+        assert_eq!(parse(&outline).unwrap(), arena);
+    }
+
+    #[test]
+    fn test_outline_errors() {
+        assert_eq!(parse("a\n  b\n\tc"), Err(ParseOutlineError::MixedIndentation { line: 3 }));
+        assert_eq!(parse("a\n \tb"), Err(ParseOutlineError::MixedIndentation { line: 2 }));
+        assert_eq!(parse("a\n  b\n   c"), Err(ParseOutlineError::InconsistentIndentation { line: 3 }));
+        assert_eq!(parse("a\n  b\n      c"), Err(ParseOutlineError::UnexpectedIndent { line: 3 }));
+        assert_eq!(parse("  a"), Err(ParseOutlineError::UnexpectedIndent { line: 1 }));
+        assert_eq!(parse("a\n  b\nc"), Err(ParseOutlineError::MultipleRoots { line: 3 }));
+        assert_eq!(parse(""), Ok(Arena::new()));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::chunks::CHUNK_SIZE;
+use crate::{Arena, Node, NodeId};
+use rayon::prelude::*;
+
+/// The depth up to which `Arena::par_fold_subtrees` folds children in
+/// parallel. Deeper subtrees are folded sequentially, which keeps the
+/// recursion shallow on degenerate trees such as long chains of operators.
+const PARALLEL_DEPTH: usize = 32;
+
+impl<T> Arena<T> {
+    /// Returns a parallel iterator over the `NodeId` and data of every node
+    /// in the `Arena`, in index order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// # use rayon::prelude::*;
+    /// let arena = &mut Arena::new();
+    /// tree!(arena, 1 => [2, 3 => [4]]);
+    ///
+    /// let sum: i32 = arena.par_iter().map(|(_, data)| data).sum();
+    /// assert_eq!(sum, 10);
+    /// ```
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = (NodeId, &T)>
+    where
+        T: Sync,
+    {
+        // The slices only need `T: Sync` to be shared between threads, unlike
+        // the reference counted chunks holding them.
+        let slices = self.nodes.slices();
+        (0..self.len()).into_par_iter().map(move |index| {
+            let node = &slices[index / CHUNK_SIZE][index % CHUNK_SIZE];
+            (node.id, &node.data)
+        })
+    }
+
+    /// Returns a parallel iterator over the `NodeId` and a mutable reference
+    /// to the data of every node in the `Arena`.
+    ///
+    /// Changes made through the iterator bypass the bookkeeping of the
+    /// arena, so it can only be used while the arena keeps no digests, has no
+    /// journal and has no listeners. Any of them can be enabled again once
+    /// the iterator is done.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena keeps digests, has a journal, or has listeners.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// # use rayon::prelude::*;
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, 1 => [2, 3 => [4]]);
+    ///
+    /// arena.par_iter_mut().for_each(|(_, data)| *data *= 10);
+    /// assert_eq!(arena.to_sexpr(root), "(10 (20) (30 (40)))");
+    /// ```
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (NodeId, &mut T)>
+    where
+        T: Send,
+    {
+        assert!(!self.has_digests(), "digests should be disabled while iterating mutably in parallel");
+        assert!(!self.has_journal(), "journal should be disabled while iterating mutably in parallel");
+        assert!(self.listeners.is_empty(), "arena should have no listeners while iterating mutably in parallel");
+
+        self.nodes.slices_mut()
+            .into_par_iter()
+            .flat_map_iter(|nodes| nodes.iter_mut().map(|node| (node.id, &mut node.data)))
+    }
+
+    /// Computes a value for every node of the tree starting at `root` from
+    /// the bottom up like `Arena::fold`, folding the subtrees of siblings in
+    /// parallel.
+    ///
+    /// `f` is called with each node and the values computed for its
+    /// children, in order. Children are always folded before their parent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "+" => ["1", "*" => ["2", "3"]]);
+    ///
+    /// // Fold the constants of the expression.
+    /// let value = arena.par_fold_subtrees(root, |node, children: Vec<i64>| {
+    ///     match *node.data() {
+    ///         "+" => children.iter().sum(),
+    ///         "*" => children.iter().product(),
+    ///         number => number.parse().unwrap(),
+    ///     }
+    /// });
+    /// assert_eq!(value, 7);
+    /// ```
+    pub fn par_fold_subtrees<U, F>(&self, root: NodeId, f: F) -> U
+    where
+        T: Send + Sync,
+        U: Send,
+        F: Fn(&Node<T>, Vec<U>) -> U + Sync,
+    {
+        self.par_fold_at(root, &f, 0)
+    }
+
+    fn par_fold_at<U, F>(&self, id: NodeId, f: &F, depth: usize) -> U
+    where
+        T: Send + Sync,
+        U: Send,
+        F: Fn(&Node<T>, Vec<U>) -> U + Sync,
+    {
+        if depth >= PARALLEL_DEPTH {
+            return self.fold(id, f);
+        }
+
+        let node = self.get(id);
+        let children = node.children()
+            .par_iter()
+            .map(|&child| self.par_fold_at(child, f, depth + 1))
+            .collect();
+        f(node, children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel() {
+        let arena = &mut Arena::new();
+        let root = arena.insert(0);
+        for i in 1..1000 {
+            let id = arena.insert(i);
+            NodeId((i - 1) / 2).add_child(arena, id);
+        }
+
+        // A long chain below the tree is folded past the parallel depth.
+        let mut last = NodeId(999);
+        for i in 1000..1100 {
+            let id = arena.insert(i);
+            last.add_child(arena, id);
+            last = id;
+        }
+
+        let sum = |node: &Node<usize>, children: Vec<usize>| node.data() + children.iter().sum::<usize>();
+        assert_eq!(arena.par_fold_subtrees(root, sum), arena.fold(root, sum));
+        assert_eq!(arena.par_fold_subtrees(root, sum), (0..1100).sum());
+
+        let ids: Vec<NodeId> = arena.par_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, (0..1100).map(NodeId).collect::<Vec<_>>());
+
+        let snapshot = arena.snapshot();
+        arena.par_iter_mut().for_each(|(id, data)| *data += id.index());
+        assert_eq!(arena.par_fold_subtrees(root, sum), 2 * (0..1100).sum::<usize>());
+        assert_eq!(snapshot.arena().par_fold_subtrees(root, sum), (0..1100).sum());
+    }
+
+    #[test]
+    fn test_par_iter_mut_bookkeeping() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let arena = &mut Arena::new();
+        tree!(arena, 1 => [2, 3]);
+        let refused = |arena: &mut Arena<i32>| {
+            panic::catch_unwind(AssertUnwindSafe(|| arena.par_iter_mut().for_each(|(_, data)| *data += 1))).is_err()
+        };
+
+        // Digests, the journal and listeners would all miss the changes.
+        arena.enable_digests();
+        assert!(refused(arena));
+        arena.disable_digests();
+
+        arena.enable_journal();
+        assert!(refused(arena));
+        arena.disable_journal();
+
+        let subscription = arena.subscribe(|_| {});
+        assert!(refused(arena));
+        arena.unsubscribe(subscription);
+
+        assert!(!refused(arena));
+        assert_eq!(arena.par_iter().map(|(_, &data)| data).sum::<i32>(), 9);
+    }
+
+    #[test]
+    fn test_par_iter_sync_only() {
+        use std::sync::MutexGuard;
+
+        // `MutexGuard` is `Sync` but not `Send`.
+        let arena: &mut Arena<(usize, Option<MutexGuard<'static, ()>>)> = &mut Arena::new();
+        for i in 0..200 {
+            arena.insert((i, None));
+        }
+
+        let sum: usize = arena.par_iter().map(|(id, data)| id.index() + data.0).sum();
+        assert_eq!(sum, 2 * (0..200).sum::<usize>());
+    }
+
+    #[test]
+    fn test_par_iter_mut_send_only() {
+        use std::cell::Cell;
+
+        // `Cell` is `Send` but not `Sync`.
+        let arena = &mut Arena::new();
+        let root = tree!(arena, Cell::new(1) => [Cell::new(2), Cell::new(3)]);
+        arena.par_iter_mut().for_each(|(_, data)| *data.get_mut() *= 10);
+        assert_eq!(arena.fold(root, |node, children: Vec<i32>| node.data().get() + children.iter().sum::<i32>()), 60);
+    }
+}
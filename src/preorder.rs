@@ -0,0 +1,35 @@
+use crate::{Arena, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+
+/// A pre-order traversal of a subtree, yielding every node along with its
+/// depth below the root of the traversal.
+pub(crate) struct Preorder<'a, T, A: Allocator = Global> {
+    arena: &'a Arena<T, A>,
+    stack: Vec<(NodeId, usize)>,
+}
+
+impl<'a, T, A> Iterator for Preorder<'a, T, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = (NodeId, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, depth) = self.stack.pop()?;
+        let children = self.arena.get(id).children();
+        self.stack.extend(children.iter().rev().map(|&child| (child, depth + 1)));
+        Some((id, depth))
+    }
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns a pre-order traversal of the subtree starting at `root`.
+    pub(crate) fn preorder(&self, root: NodeId) -> Preorder<'_, T, A> {
+        Preorder { arena: self, stack: vec![(root, 0)] }
+    }
+}
@@ -0,0 +1,481 @@
+use crate::scan::{ParseError, Scanner};
+use crate::{Arena, Match, NodeId, Tree};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{self, Debug, Display};
+
+/// A pattern matching the shape of a subtree, used by `Rewriter` rules.
+///
+/// A pattern is a kind name, which matches any node of that kind, optionally
+/// followed by a parenthesized list of patterns, which must match the
+/// children of the node one by one. The kind name `_` matches a node of any
+/// kind. A pattern can be prefixed with `$name:` to capture the node it
+/// matched, and `$name` on its own captures a node of any kind and shape.
+///
+/// | Pattern             | Matches                                        |
+/// |---------------------|------------------------------------------------|
+/// | `Group`             | a `Group` with any children                    |
+/// | `Group()`           | a `Group` without children                     |
+/// | `Group($x:Token)`   | a `Group` with a single `Token` child, as `x`  |
+/// | `Binary($l, _, $r)` | a `Binary` with three children of any kind     |
+///
+/// The kind of a node is obtained from its data with the closure given to
+/// `Pattern::parse`, which may capture state such as a table of kind names.
+pub struct Pattern<T> {
+    kind: Rc<dyn Fn(&T) -> &str>,
+    root: PatternNode,
+}
+
+#[derive(Clone, Debug)]
+struct PatternNode {
+    capture: Option<String>,
+    kind: Option<String>,
+    children: Option<Vec<PatternNode>>,
+}
+
+impl<T> Pattern<T> {
+    /// Parses a pattern, using `kind` to obtain the kind of a node from its
+    /// data while matching.
+    pub fn parse<K>(text: &str, kind: K) -> Result<Self, ParseError>
+    where
+        K: Fn(&T) -> &str + 'static,
+    {
+        let mut parser = Parser { scanner: Scanner::new(text) };
+        let root = parser.parse_pattern()?;
+        parser.scanner.finish()?;
+
+        Ok(Self { kind: Rc::new(kind), root })
+    }
+
+    /// Matches the subtree at `id` against the pattern, returning the nodes
+    /// it captured if it matches.
+    pub fn match_at<'p, A>(&'p self, tree: &A, id: NodeId) -> Option<Match<'p>>
+    where
+        A: Tree<Data = T>,
+    {
+        let mut captures = Vec::new();
+
+        if self.match_node(tree, id, &self.root, &mut captures) {
+            Some(Match { id, captures })
+        } else {
+            None
+        }
+    }
+
+    fn match_node<'p, A>(
+        &self,
+        tree: &A,
+        id: NodeId,
+        pattern: &'p PatternNode,
+        captures: &mut Vec<(&'p str, NodeId)>,
+    ) -> bool
+    where
+        A: Tree<Data = T>,
+    {
+        if let Some(kind) = &pattern.kind {
+            match tree.data(id) {
+                Some(data) if (self.kind)(data) == kind => {},
+                _ => return false,
+            }
+        }
+
+        if let Some(name) = &pattern.capture {
+            captures.push((name, id));
+        }
+
+        match &pattern.children {
+            Some(children) => {
+                children.len() == tree.children(id).len()
+                    && children.iter()
+                        .zip(tree.children(id))
+                        .all(|(pattern, &child)| self.match_node(tree, child, pattern, captures))
+            },
+            None => true,
+        }
+    }
+}
+
+impl<T> Clone for Pattern<T> {
+    fn clone(&self) -> Self {
+        Self { kind: Rc::clone(&self.kind), root: self.root.clone() }
+    }
+}
+
+impl<T> Debug for Pattern<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pattern").field("root", &self.root).finish()
+    }
+}
+
+struct Parser<'a> {
+    scanner: Scanner<'a>,
+}
+
+impl Parser<'_> {
+    /// Consumes `expected` if it is the next character after any whitespace.
+    fn eat(&mut self, expected: char) -> bool {
+        self.scanner.skip_whitespace();
+        self.scanner.eat(expected)
+    }
+
+    fn ident(&mut self) -> Result<&str, ParseError> {
+        self.scanner.skip_whitespace();
+        self.scanner.ident()
+    }
+
+    fn parse_pattern(&mut self) -> Result<PatternNode, ParseError> {
+        if !self.eat('$') {
+            return self.parse_node();
+        }
+
+        let capture = Some(self.ident()?.to_string());
+
+        if self.eat(':') {
+            Ok(PatternNode { capture, ..self.parse_node()? })
+        } else {
+            Ok(PatternNode { capture, kind: None, children: None })
+        }
+    }
+
+    fn parse_node(&mut self) -> Result<PatternNode, ParseError> {
+        let kind = match self.ident()? {
+            "_" => None,
+            kind => Some(kind.to_string()),
+        };
+
+        if !self.eat('(') {
+            return Ok(PatternNode { capture: None, kind, children: None });
+        }
+
+        let mut children = Vec::new();
+
+        while !self.eat(')') {
+            children.push(self.parse_pattern()?);
+
+            if !self.eat(',') {
+                if self.eat(')') {
+                    break;
+                }
+                return Err(self.scanner.unexpected());
+            }
+        }
+
+        Ok(PatternNode { capture: None, kind, children: Some(children) })
+    }
+}
+
+type Replace<'r, T> = Box<dyn FnMut(&mut Arena<T>, &Match) -> Option<NodeId> + 'r>;
+
+/// A set of rewrite rules, applied to a tree until none of them match.
+///
+/// Each rule pairs a `Pattern` with a replacement function. When the pattern
+/// matches a node, the function is called with the arena and the `Match`,
+/// and returns the node to put in its place: either one of the captured
+/// nodes, or a new node built in the arena. Returning `None` declines the
+/// rewrite, which allows rules to check conditions patterns cannot express.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena, Pattern, Rewriter};
+/// fn kind<'a>(data: &'a &str) -> &'a str {
+///     if data.chars().all(char::is_alphabetic) { *data } else { "Token" }
+/// }
+///
+/// // (((1)) + (2))
+/// let arena = &mut Arena::new();
+/// let root = tree!(arena, "Group" => [
+///     "Binary" => ["Group" => ["Group" => ["1"]], "+", "Group" => ["2"]],
+/// ]);
+///
+/// // Group(x) => x when x is a token.
+/// let mut rewriter = Rewriter::new()
+///     .rule(Pattern::parse("Group($x:Token)", kind).unwrap(), |_, m| m.capture("x"));
+///
+/// let rewritten = rewriter.rewrite(arena, root).unwrap();
+/// assert_eq!(rewritten.log.len(), 3);
+/// assert_eq!(arena.to_sexpr(rewritten.root), "(Group (Binary (1) (+) (2)))");
+/// ```
+pub struct Rewriter<'r, T> {
+    rules: Vec<(Pattern<T>, Replace<'r, T>)>,
+    step_limit: usize,
+}
+
+impl<'r, T> Rewriter<'r, T> {
+    /// Creates a new `Rewriter` without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule replacing the nodes matched by `pattern` with the node
+    /// returned by `replace`.
+    ///
+    /// Rules are tried in the order they were added, and the first one to
+    /// rewrite a node wins.
+    pub fn rule<F>(mut self, pattern: Pattern<T>, replace: F) -> Self
+    where
+        F: FnMut(&mut Arena<T>, &Match) -> Option<NodeId> + 'r,
+    {
+        self.rules.push((pattern, Box::new(replace)));
+        self
+    }
+
+    /// Sets the maximum number of rewrites applied by a single call to
+    /// `Rewriter::rewrite`, which defaults to 10,000.
+    ///
+    /// The limit guards against rules which never reach a fixpoint, such as
+    /// rules undoing each other.
+    pub fn step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    /// Rewrites the subtree at `root` in place, applying the rules bottom-up
+    /// until none of them match.
+    ///
+    /// A rewritten node is detached from the tree, and the replacement takes
+    /// its position among its parent's children. If `root` itself is
+    /// rewritten, the replacement becomes the new root of the subtree, and of
+    /// the arena if `root` was its root. Nodes removed from the tree remain in
+    /// the arena.
+    ///
+    /// Returns an error if the step limit is reached, in which case the
+    /// rewrites applied so far are kept.
+    ///
+    /// When the journal of the arena is enabled, the whole rewrite, including
+    /// the changes made by the replacement closures, is recorded as a single
+    /// undo step. The closures must therefore not begin, commit or undo
+    /// transactions of their own.
+    pub fn rewrite(&mut self, arena: &mut Arena<T>, root: NodeId) -> Result<Rewritten, RewriteError> {
+        arena.grouped(|arena| self.rewrite_all(arena, root))
+    }
+
+    fn rewrite_all(&mut self, arena: &mut Arena<T>, root: NodeId) -> Result<Rewritten, RewriteError> {
+        let mut root = root;
+        let mut log = Vec::new();
+
+        loop {
+            let mut changed = false;
+
+            for id in postorder(arena, root) {
+                for (rule, (pattern, replace)) in self.rules.iter_mut().enumerate() {
+                    let new = match pattern.match_at(arena, id) {
+                        Some(found) => replace(arena, &found),
+                        None => continue,
+                    };
+
+                    let new = match new {
+                        Some(new) if new != id => new,
+                        _ => continue,
+                    };
+
+                    if log.len() == self.step_limit {
+                        return Err(RewriteError::StepLimit { limit: self.step_limit });
+                    }
+
+                    splice(arena, id, new);
+                    if id == root {
+                        root = new;
+                    }
+
+                    log.push(RewriteStep { rule, old: id, new });
+                    changed = true;
+                    break;
+                }
+            }
+
+            if !changed {
+                return Ok(Rewritten { root, log });
+            }
+        }
+    }
+}
+
+impl<'r, T> Default for Rewriter<'r, T> {
+    fn default() -> Self {
+        Self { rules: Vec::new(), step_limit: 10_000 }
+    }
+}
+
+/// Returns the nodes of the subtree at `root` in reverse pre-order.
+fn postorder<T>(arena: &Arena<T>, root: NodeId) -> Vec<NodeId> {
+    let mut order: Vec<NodeId> = arena.preorder(root).map(|(id, _)| id).collect();
+
+    // This visits siblings from right to left, but every node still comes
+    // after its descendants, which is all a bottom-up rewrite relies on.
+    order.reverse();
+    order
+}
+
+/// Puts `new` in the place of `old`, detaching both from their parents.
+fn splice<T>(arena: &mut Arena<T>, old: NodeId, new: NodeId) {
+    arena.detach(new);
+
+    if let Some(parent) = arena.get(old).parent() {
+        let index = arena.get(parent).children().iter()
+            .position(|&child| child == old)
+            .expect("node should be a child of its parent");
+        arena.detach(old);
+        arena.attach(parent, index, new);
+    }
+
+    if arena.root() == Some(old) {
+        arena.set_root(new);
+    }
+}
+
+/// The result of `Rewriter::rewrite`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rewritten {
+    /// The root of the rewritten subtree.
+    pub root: NodeId,
+    /// Every rewrite that was applied, in order.
+    pub log: Vec<RewriteStep>,
+}
+
+/// A single rewrite applied by a `Rewriter`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RewriteStep {
+    /// The index of the rule that was applied, in the order rules were added.
+    pub rule: usize,
+    /// The node that was replaced.
+    pub old: NodeId,
+    /// The node that replaced it.
+    pub new: NodeId,
+}
+
+/// An error which can be returned by `Rewriter::rewrite`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RewriteError {
+    /// The rules were applied the given number of times without reaching a
+    /// fixpoint.
+    StepLimit { limit: usize },
+}
+
+impl Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StepLimit { limit } => {
+                write!(f, "rewrite did not finish within {} steps", limit)
+            },
+        }
+    }
+}
+
+impl Error for RewriteError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind<'a>(data: &'a &str) -> &'a str {
+        data.split(':').next().unwrap()
+    }
+
+    #[test]
+    fn test_pattern() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "B:1" => ["T:a", "T:+", "G:2" => ["T:b"]]);
+        let children = root.children(arena).to_vec();
+
+        let pattern = Pattern::parse("B($l:T, _, $r:G(T))", kind).unwrap();
+        let found = pattern.match_at(arena, root).unwrap();
+        assert_eq!(found.captures(), &[("l", children[0]), ("r", children[2])]);
+
+        let matches = |text| Pattern::parse(text, kind).unwrap().match_at(arena, root).is_some();
+        assert!(matches("B"));
+        assert!(matches("$x"));
+        assert!(matches(" B ( T , T , G ( $y ) , ) "));
+        assert!(!matches("B()"));
+        assert!(!matches("B(T, T)"));
+        assert!(!matches("B(T, T, G())"));
+        assert!(!matches("G"));
+
+        // The kind hook can capture state, here the names of the short kinds.
+        let names = [("B", "Binary"), ("G", "Group"), ("T", "Token")];
+        let pattern = Pattern::parse("Binary(Token, _, Group)", move |data: &&str| {
+            let short = kind(data);
+            names.iter().find(|&&(name, _)| name == short).map_or(short, |&(_, long)| long)
+        });
+        let pattern = pattern.unwrap();
+        assert!(pattern.clone().match_at(arena, root).is_some());
+
+        let parse = |text| Pattern::parse(text, kind).map(|_| ());
+        assert_eq!(parse(""), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("B(T"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("B(,)"), Err(ParseError::UnexpectedChar { pos: 2, found: ',' }));
+        assert_eq!(parse("B T"), Err(ParseError::UnexpectedChar { pos: 2, found: 'T' }));
+        assert_eq!(parse("$:T"), Err(ParseError::UnexpectedChar { pos: 1, found: ':' }));
+    }
+
+    #[test]
+    fn test_rewrite() {
+        // -(-(a)) * (b)
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "B:*" => [
+            "U:-" => ["G" => ["U:-" => ["G" => ["T:a"]]]],
+            "T:*",
+            "G" => ["T:b"],
+        ]);
+        let unrelated = tree!(arena, "G" => ["T:c"]);
+
+        let mut rewriter = Rewriter::new()
+            .rule(Pattern::parse("G($x:T)", kind).unwrap(), |_, m| m.capture("x"))
+            .rule(Pattern::parse("U(G($x:U($y)))", kind).unwrap(), |_, m| m.capture("y"))
+            .rule(Pattern::parse("T", kind).unwrap(), |_, _| None);
+
+        let rewritten = rewriter.rewrite(arena, root).unwrap();
+        assert_eq!(rewritten.root, root);
+        assert_eq!(arena.to_sexpr(root), "(B:* (T:a) (T:*) (T:b))");
+        assert_eq!(rewritten.log.iter().map(|step| step.rule).collect::<Vec<_>>(), [0, 0, 1]);
+        assert_eq!(arena.to_sexpr(unrelated), "(G (T:c))");
+
+        // Rewriting the root updates the root of the arena.
+        let mut rewriter = Rewriter::new()
+            .rule(Pattern::parse("B($x, _, _)", kind).unwrap(), |_, m| m.capture("x"));
+
+        let rewritten = rewriter.rewrite(arena, root).unwrap();
+        assert_eq!(arena.root(), Some(rewritten.root));
+        assert_eq!(arena.to_sexpr(rewritten.root), "(T:a)");
+        assert_eq!(rewritten.root.parent(arena), None);
+
+        // Rules that never reach a fixpoint run into the step limit.
+        let mut rewriter = Rewriter::new()
+            .rule(Pattern::parse("T", kind).unwrap(), |arena, m| {
+                let data = *arena.node_at(m.id()).unwrap().data();
+                Some(arena.insert(data))
+            })
+            .step_limit(5);
+
+        assert_eq!(
+            rewriter.rewrite(arena, rewritten.root),
+            Err(RewriteError::StepLimit { limit: 5 }),
+        );
+    }
+
+    #[test]
+    fn test_rewrite_undo() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "B:+" => ["G" => ["G" => ["T:a"]], "T:+", "G" => ["T:b"]]);
+        let text = arena.to_sexpr(root);
+        arena.enable_journal();
+
+        // Every rewrite, and every node inserted by a rule, is undone at once.
+        let mut rewriter = Rewriter::new()
+            .rule(Pattern::parse("G($x)", kind).unwrap(), |_, m| m.capture("x"))
+            .rule(Pattern::parse("T", kind).unwrap(), |arena, m| {
+                let data = *arena.node_at(m.id()).unwrap().data();
+                if data.ends_with('!') { None } else { Some(arena.insert("T:!")) }
+            });
+
+        rewriter.rewrite(arena, root).unwrap();
+        assert_eq!(arena.to_sexpr(root), "(B:+ (T:!) (T:!) (T:!))");
+        assert!(arena.undo());
+        assert!(!arena.undo());
+        assert_eq!(arena.to_sexpr(root), text);
+        assert_eq!(arena.len(), 7);
+    }
+}
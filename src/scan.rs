@@ -0,0 +1,129 @@
+use core::convert::Infallible;
+use core::error::Error;
+use core::fmt::{self, Display};
+
+/// A cursor over the text given to one of the parsers of this crate, which
+/// keeps track of the byte offset reported in errors.
+pub(crate) struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    /// Returns the byte offset of the next character.
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the text which has not been consumed yet.
+    pub(crate) fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    /// Consumes the next `len` bytes of the text.
+    pub(crate) fn advance(&mut self, len: usize) {
+        self.pos += len;
+    }
+
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Consumes any whitespace, returning the number of bytes skipped.
+    pub(crate) fn skip_whitespace(&mut self) -> usize {
+        let rest = self.rest();
+        let len = rest.len() - rest.trim_start().len();
+        self.pos += len;
+        len
+    }
+
+    /// Consumes the next character if it is `expected`.
+    pub(crate) fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the next character, which must be `expected`.
+    pub(crate) fn expect<E>(&mut self, expected: char) -> Result<(), ParseError<E>> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Consumes a name made of alphanumeric characters, `_` and `-`.
+    pub(crate) fn ident<E>(&mut self) -> Result<&'a str, ParseError<E>> {
+        let rest = self.rest();
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(self.unexpected());
+        }
+
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    /// Consumes any trailing whitespace, which must be all that is left.
+    pub(crate) fn finish<E>(&mut self) -> Result<(), ParseError<E>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(_) => Err(self.unexpected()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the error for the next character, or for the end of the text.
+    pub(crate) fn unexpected<E>(&self) -> ParseError<E> {
+        match self.peek() {
+            Some(found) => ParseError::UnexpectedChar { pos: self.pos, found },
+            None => ParseError::UnexpectedEnd,
+        }
+    }
+}
+
+/// An error which can be returned when parsing a `Selector`, a `Pattern`, or
+/// an S-expression with `Arena::from_sexpr`.
+///
+/// Only `Arena::from_sexpr` calls back into user code to parse labels, so
+/// the errors of `Selector::parse` and `Pattern::parse` never hold a
+/// `ParseError::Leaf`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError<E = Infallible> {
+    /// The input ended before it was complete.
+    UnexpectedEnd,
+    /// An unexpected character was found at the given byte offset.
+    UnexpectedChar { pos: usize, found: char },
+    /// The label at the given byte offset was rejected by the leaf parser.
+    Leaf { pos: usize, error: E },
+}
+
+impl<E> Display for ParseError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => {
+                write!(f, "unexpected end of input")
+            },
+            Self::UnexpectedChar { pos, found } => {
+                write!(f, "unexpected character `{}` at offset {}", found, pos)
+            },
+            Self::Leaf { pos, error } => {
+                write!(f, "invalid label at offset {}: {}", pos, error)
+            },
+        }
+    }
+}
+
+impl<E> Error for ParseError<E> where E: Error {}
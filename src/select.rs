@@ -0,0 +1,477 @@
+use crate::collections::Set;
+use crate::scan::{ParseError, Scanner};
+use crate::{Arena, NodeId, Tree};
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::fmt::{self, Debug};
+use core::iter;
+
+/// A pattern matching nodes by their kind and their position relative to
+/// other nodes, in the style of CSS selectors.
+///
+/// A selector is a sequence of simple selectors separated by combinators. A
+/// simple selector is either a kind name, which matches nodes whose kind is
+/// equal to it, or `*`, which matches any node. Each simple selector can be
+/// followed by `@name` to capture the node it matched under that name.
+///
+/// | Combinator | Matches `b` when                        |
+/// |------------|-----------------------------------------|
+/// | `a b`      | `b` is a descendant of `a`              |
+/// | `a > b`    | `b` is a child of `a`                   |
+/// | `a + b`    | `b` directly follows its sibling `a`    |
+/// | `a ~ b`    | `b` follows its sibling `a`             |
+///
+/// The kind of a node is obtained from its data with the closure given to
+/// `Selector::parse`, which may capture state such as a table of kind
+/// names.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena, Selector};
+/// let arena = &mut Arena::new();
+/// let root = tree!(arena, "binary" => ["1", "+", "unary" => ["-", "2"]]);
+///
+/// fn kind<'a>(data: &'a &str) -> &'a str {
+///     if data.chars().all(char::is_alphabetic) { *data } else { "token" }
+/// }
+///
+/// let selector = Selector::parse("binary > unary > token", kind).unwrap();
+/// assert_eq!(arena.select(root, &selector).count(), 2);
+///
+/// let selector = Selector::parse("token@lhs + token@op", kind).unwrap();
+/// let found: Vec<_> = arena.select_matches(root, &selector).collect();
+/// assert_eq!(found.len(), 2);
+/// assert_eq!(found[0].capture("lhs"), Some(root.children(arena)[0]));
+/// assert_eq!(found[0].capture("op"), Some(found[0].id()));
+/// ```
+pub struct Selector<T> {
+    kind: Rc<dyn Fn(&T) -> &str>,
+    steps: Vec<Step>,
+}
+
+/// A simple selector together with the combinator relating it to the
+/// previous one.
+#[derive(Clone, Debug)]
+struct Step {
+    combinator: Combinator,
+    kind: Option<String>,
+    capture: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    Adjacent,
+    Sibling,
+}
+
+/// The searches which are known to fail for a selector and a root, so that
+/// matching never repeats them. Successful searches end the match, so only
+/// failures need to be remembered.
+#[derive(Default)]
+struct Memo {
+    failed: Set<(Search, usize, NodeId)>,
+}
+
+/// A search for a node matching the steps of a selector up to some index.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Search {
+    /// The node itself matches.
+    Node,
+    /// The node or one of its ancestors within the root matches.
+    Ancestors,
+    /// The node or one of its previous siblings matches.
+    Siblings,
+}
+
+impl<T> Selector<T> {
+    /// Parses a selector, using `kind` to obtain the kind of a node from its
+    /// data while matching.
+    pub fn parse<K>(text: &str, kind: K) -> Result<Self, ParseError>
+    where
+        K: Fn(&T) -> &str + 'static,
+    {
+        let mut scanner = Scanner::new(text);
+        let mut steps = Vec::new();
+        let mut combinator = None;
+
+        loop {
+            let skipped = scanner.skip_whitespace();
+
+            let found = match scanner.peek() {
+                Some(found) => found,
+                None => break,
+            };
+
+            let explicit = match found {
+                '>' => Some(Combinator::Child),
+                '+' => Some(Combinator::Adjacent),
+                '~' => Some(Combinator::Sibling),
+                _ => None,
+            };
+
+            if let Some(explicit) = explicit {
+                if steps.is_empty() || combinator.is_some() {
+                    return Err(scanner.unexpected());
+                }
+
+                combinator = Some(explicit);
+                scanner.advance(found.len_utf8());
+                continue;
+            }
+
+            if !steps.is_empty() && combinator.is_none() && skipped == 0 {
+                return Err(scanner.unexpected());
+            }
+
+            let kind = if scanner.eat('*') {
+                None
+            } else {
+                Some(scanner.ident()?.to_string())
+            };
+
+            let capture = if scanner.eat('@') {
+                Some(scanner.ident()?.to_string())
+            } else {
+                None
+            };
+
+            steps.push(Step {
+                combinator: combinator.take().unwrap_or(Combinator::Descendant),
+                kind,
+                capture,
+            });
+        }
+
+        if steps.is_empty() || combinator.is_some() {
+            return Err(ParseError::UnexpectedEnd);
+        }
+
+        Ok(Self { kind: Rc::new(kind), steps })
+    }
+
+    /// Returns `true` if the node at `id` is matched by the selector, without
+    /// looking at any nodes outside of the subtree at `root`.
+    pub fn matches<A>(&self, tree: &A, root: NodeId, id: NodeId) -> bool
+    where
+        A: Tree<Data = T>,
+    {
+        self.match_at(tree, root, id, &mut Memo::default()).is_some()
+    }
+
+    fn match_at<'s, A>(&'s self, tree: &A, root: NodeId, id: NodeId, memo: &mut Memo) -> Option<Match<'s>>
+    where
+        A: Tree<Data = T>,
+    {
+        let mut captures = Vec::new();
+
+        if self.match_step(tree, root, id, self.steps.len() - 1, &mut captures, memo) {
+            captures.reverse();
+            Some(Match { id, captures })
+        } else {
+            None
+        }
+    }
+
+    /// Matches the node at `id` against the step at `index`, then searches
+    /// for a node matching the previous step, backtracking on failure.
+    fn match_step<'s, A>(
+        &'s self,
+        tree: &A,
+        root: NodeId,
+        id: NodeId,
+        index: usize,
+        captures: &mut Vec<(&'s str, NodeId)>,
+        memo: &mut Memo,
+    ) -> bool
+    where
+        A: Tree<Data = T>,
+    {
+        if memo.failed.contains(&(Search::Node, index, id)) {
+            return false;
+        }
+
+        let step = &self.steps[index];
+
+        if let Some(kind) = &step.kind {
+            match tree.data(id) {
+                Some(data) if (self.kind)(data) == kind => {},
+                _ => {
+                    memo.failed.insert((Search::Node, index, id));
+                    return false;
+                },
+            }
+        }
+
+        if index == 0 {
+            if let Some(name) = &step.capture {
+                captures.push((name, id));
+            }
+            return true;
+        }
+
+        let parent = match tree.parent(id) {
+            Some(parent) if id != root => parent,
+            _ => {
+                memo.failed.insert((Search::Node, index, id));
+                return false;
+            },
+        };
+
+        let len = captures.len();
+        if let Some(name) = &step.capture {
+            captures.push((name, id));
+        }
+
+        let siblings = tree.children(parent);
+        let previous = || {
+            let position = siblings.iter().position(|&sibling| sibling == id).unwrap_or(0);
+            siblings[..position].iter().rev().copied()
+        };
+
+        let found = match step.combinator {
+            Combinator::Child => self.match_step(tree, root, parent, index - 1, captures, memo),
+            Combinator::Descendant => {
+                let ancestors = iter::successors(Some(parent), |&current| {
+                    if current == root { None } else { tree.parent(current) }
+                });
+                self.search(tree, root, Search::Ancestors, ancestors, index - 1, captures, memo)
+            },
+            Combinator::Adjacent => previous().next()
+                .is_some_and(|sibling| self.match_step(tree, root, sibling, index - 1, captures, memo)),
+            Combinator::Sibling => self.search(tree, root, Search::Siblings, previous(), index - 1, captures, memo),
+        };
+
+        if !found {
+            captures.truncate(len);
+            memo.failed.insert((Search::Node, index, id));
+        }
+        found
+    }
+
+    /// Matches each of `candidates` against the step at `index` in turn,
+    /// where every candidate is followed by the rest of the candidates of the
+    /// same kind of search from it. The search stops at the first candidate
+    /// known to fail, as the rest of the candidates would fail too.
+    #[allow(clippy::too_many_arguments)]
+    fn search<'s, A, I>(
+        &'s self,
+        tree: &A,
+        root: NodeId,
+        search: Search,
+        candidates: I,
+        index: usize,
+        captures: &mut Vec<(&'s str, NodeId)>,
+        memo: &mut Memo,
+    ) -> bool
+    where
+        A: Tree<Data = T>,
+        I: Iterator<Item = NodeId>,
+    {
+        let mut visited = Vec::new();
+
+        for candidate in candidates {
+            if memo.failed.contains(&(search, index, candidate)) {
+                break;
+            }
+            if self.match_step(tree, root, candidate, index, captures, memo) {
+                return true;
+            }
+            visited.push(candidate);
+        }
+
+        memo.failed.extend(visited.into_iter().map(|candidate| (search, index, candidate)));
+        false
+    }
+}
+
+impl<T> Clone for Selector<T> {
+    fn clone(&self) -> Self {
+        Self { kind: Rc::clone(&self.kind), steps: self.steps.clone() }
+    }
+}
+
+impl<T> Debug for Selector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Selector").field("steps", &self.steps).finish()
+    }
+}
+
+/// A node matched by a `Selector` or `Pattern`, along with the nodes it
+/// captured.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Match<'s> {
+    pub(crate) id: NodeId,
+    pub(crate) captures: Vec<(&'s str, NodeId)>,
+}
+
+impl<'s> Match<'s> {
+    /// Returns the `NodeId` of the matched node.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Returns the node captured under `name`, if any.
+    pub fn capture(&self, name: &str) -> Option<NodeId> {
+        self.captures.iter()
+            .find(|&&(capture, _)| capture == name)
+            .map(|&(_, id)| id)
+    }
+
+    /// Returns every capture, in the order they appear in the selector or
+    /// pattern.
+    pub fn captures(&self) -> &[(&'s str, NodeId)] {
+        &self.captures
+    }
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the nodes in the subtree at `root` which are matched by
+    /// `selector`, in pre-order.
+    ///
+    /// Only nodes within the subtree are considered when matching
+    /// combinators, so `root` has no ancestors or siblings as far as the
+    /// selector is concerned.
+    pub fn select<'a>(&'a self, root: NodeId, selector: &'a Selector<T>) -> impl Iterator<Item = NodeId> + 'a {
+        self.select_matches(root, selector).map(|found| found.id)
+    }
+
+    /// Returns the matches of `selector` in the subtree at `root`, in
+    /// pre-order, along with the nodes captured by each of them.
+    ///
+    /// When a node can be matched in several ways, the captures are taken
+    /// from the first one found, preferring the closest ancestors and
+    /// siblings.
+    ///
+    /// Searches which failed for one node are remembered for the rest of the
+    /// subtree, so every node is tried at most once against each step of the
+    /// selector and each kind of combinator.
+    pub fn select_matches<'a>(&'a self, root: NodeId, selector: &'a Selector<T>) -> impl Iterator<Item = Match<'a>> + 'a {
+        let mut memo = Memo::default();
+        self.preorder(root)
+            .filter_map(move |(id, _)| selector.match_at(self, root, id, &mut memo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind<'a>(data: &&'a str) -> &'a str {
+        data.split(':').next().unwrap()
+    }
+
+    #[test]
+    fn test_select() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "a:1" => [
+            "b:2" => ["c:3", "b:4" => ["c:5"]],
+            "c:6",
+            "d:7",
+            "c:8",
+        ]);
+
+        let select = |text| -> Vec<&str> {
+            let selector = Selector::parse(text, |data: &&str| kind(data)).unwrap();
+            arena.select(root, &selector)
+                .map(|id| &arena.node_at(id).unwrap().data()[2..])
+                .collect()
+        };
+
+        assert_eq!(select("c"), ["3", "5", "6", "8"]);
+        assert_eq!(select("a c"), ["3", "5", "6", "8"]);
+        assert_eq!(select("a > c"), ["6", "8"]);
+        assert_eq!(select("b c"), ["3", "5"]);
+        assert_eq!(select("b b > c"), ["5"]);
+        assert_eq!(select("a>b>*"), ["3", "4"]);
+        assert_eq!(select("c + *"), ["4", "7"]);
+        assert_eq!(select("b ~ c"), ["6", "8"]);
+        assert_eq!(select("b~d+c"), ["8"]);
+        assert_eq!(select("x"), Vec::<&str>::new());
+
+        // Nodes outside of the subtree are not considered.
+        let b = root.children(arena)[0];
+        let selector = Selector::parse("a c", |data: &&str| kind(data)).unwrap();
+        assert_eq!(arena.select(b, &selector).count(), 0);
+        assert!(selector.matches(arena, root, b.children(arena)[0]));
+        assert!(!selector.matches(arena, b, b.children(arena)[0]));
+    }
+
+    #[test]
+    fn test_select_matches() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "a:1" => ["b:2" => ["b:3" => ["c:4"]]]);
+        let selector = Selector::parse("b@outer * > c@inner", |data: &&str| kind(data)).unwrap();
+
+        let found: Vec<Match> = arena.select_matches(root, &selector).collect();
+        let b2 = root.children(arena)[0];
+        let b3 = b2.children(arena)[0];
+        let c4 = b3.children(arena)[0];
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id(), c4);
+        assert_eq!(found[0].captures(), &[("outer", b2), ("inner", c4)]);
+        assert_eq!(found[0].capture("inner"), Some(c4));
+        assert_eq!(found[0].capture("missing"), None);
+
+        let parse = |text| Selector::parse(text, |data: &&str| kind(data)).map(|_| ());
+        assert_eq!(parse(""), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("a >"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("a@"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("> a"), Err(ParseError::UnexpectedChar { pos: 0, found: '>' }));
+        assert_eq!(parse("a > + b"), Err(ParseError::UnexpectedChar { pos: 4, found: '+' }));
+        assert_eq!(parse("a*"), Err(ParseError::UnexpectedChar { pos: 1, found: '*' }));
+        assert_eq!(parse("a.b"), Err(ParseError::UnexpectedChar { pos: 1, found: '.' }));
+    }
+
+    #[test]
+    fn test_select_capturing_kind() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, 1 => [2, 3 => [4, 5]]);
+
+        // The table of kind names is owned by the closure.
+        let names = ["even", "odd"];
+        let selector = Selector::parse("odd > even", move |&data: &i32| names[data as usize % 2]).unwrap();
+
+        assert_eq!(arena.select(root, &selector).collect::<Vec<_>>(), vec![NodeId(1), NodeId(3)]);
+        assert!(!selector.clone().matches(arena, root, NodeId(4)));
+    }
+
+    #[test]
+    fn test_select_deep_chain() {
+        let arena = &mut Arena::new();
+        let root = arena.insert("a");
+        let mut bottom = root;
+        for _ in 1..2_000 {
+            let id = arena.insert("a");
+            bottom.add_child(arena, id);
+            bottom = id;
+        }
+
+        let wide = arena.insert("w");
+        bottom.add_child(arena, wide);
+        for _ in 0..2_000 {
+            let id = arena.insert("a");
+            wide.add_child(arena, id);
+        }
+
+        // Failed searches are not repeated, so a selector which can never
+        // match gives up quickly instead of retrying every ancestor.
+        let count = |text| {
+            let selector = Selector::parse(text, |data: &&str| *data).unwrap();
+            arena.select(root, &selector).count()
+        };
+        assert_eq!(count("x a a a a a a"), 0);
+        assert_eq!(count("x ~ a ~ a ~ a ~ a"), 0);
+        assert_eq!(count("a a a a a a"), 2_000 - 5 + 2_000);
+        assert_eq!(count("a ~ a ~ a ~ a"), 2_000 - 3);
+        assert_eq!(count("a w > a + a"), 2_000 - 1);
+    }
+}
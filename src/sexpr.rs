@@ -0,0 +1,308 @@
+use crate::scan::{ParseError, Scanner};
+use crate::{Arena, NodeId, Tree};
+use alloc::string::String;
+use alloc::vec;
+use allocator_api2::alloc::Allocator;
+use core::fmt::{self, Display, Write};
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Renders the subtree starting at `root` as an S-expression.
+    ///
+    /// Every node is written as a parenthesised list whose head is the
+    /// `Display` output of its data, followed by each of its children in
+    /// order. Labels are written verbatim, unless they are empty, start or
+    /// end with whitespace, or contain parentheses, quotes or backslashes.
+    /// Such labels are written in double quotes, with `"` and `\` escaped by
+    /// a backslash, so that `Arena::from_sexpr` reads them back unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let arena = &mut Arena::new();
+    /// let plus = arena.insert("+");
+    /// let one = arena.insert("1");
+    /// let two = arena.insert("2");
+    ///
+    /// plus
+    ///     .add_child(arena, one)
+    ///     .add_child(arena, two);
+    ///
+    /// assert_eq!(arena.to_sexpr(plus), "(+ (1) (2))");
+    ///
+    /// let paren = arena.insert("(");
+    /// two.add_child(arena, paren);
+    /// assert_eq!(arena.to_sexpr(two), r#"(2 ("("))"#);
+    /// ```
+    pub fn to_sexpr(&self, root: NodeId) -> String
+    where
+        T: Display,
+    {
+        to_sexpr(self, root)
+    }
+}
+
+impl<T> Arena<T> {
+    /// Builds a new `Arena` from an S-expression produced by
+    /// `Arena::to_sexpr`.
+    ///
+    /// The head of every list (the text up to its first nested list or its
+    /// closing parenthesis, with surrounding whitespace trimmed, or the
+    /// unescaped contents of a quoted label) is passed to `parse_leaf` to
+    /// produce the data of that node. The outermost list
+    /// becomes the root of the new arena.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let arena = Arena::from_sexpr("(+ (1) (2))", |label| {
+    ///     Ok::<_, ()>(label.to_string())
+    /// }).unwrap();
+    ///
+    /// let root = arena.root().unwrap();
+    /// assert_eq!(root.children(&arena).len(), 2);
+    /// assert_eq!(arena.to_sexpr(root), "(+ (1) (2))");
+    /// ```
+    pub fn from_sexpr<F, E>(text: &str, parse_leaf: F) -> Result<Self, ParseError<E>>
+    where
+        F: FnMut(&str) -> Result<T, E>,
+    {
+        let mut parser = Parser {
+            scanner: Scanner::new(text),
+            arena: Arena::new(),
+            parse_leaf,
+        };
+
+        parser.scanner.skip_whitespace();
+        parser.parse_tree()?;
+        parser.scanner.finish()?;
+
+        Ok(parser.arena)
+    }
+}
+
+/// Renders the subtree starting at `root` as an S-expression.
+pub(crate) fn to_sexpr<A>(tree: &A, root: NodeId) -> String
+where
+    A: Tree + ?Sized,
+    A::Data: Display,
+{
+    let mut out = String::new();
+    write_sexpr(tree, root, &mut out).expect("writing to a `String` cannot fail");
+    out
+}
+
+fn write_sexpr<A>(tree: &A, root: NodeId, out: &mut String) -> fmt::Result
+where
+    A: Tree + ?Sized,
+    A::Data: Display,
+{
+    // `None` closes the list of the node opened before it.
+    let mut stack = vec![Some(root)];
+    let mut label = String::new();
+
+    while let Some(entry) = stack.pop() {
+        match entry {
+            Some(id) => {
+                if id != root {
+                    out.push(' ');
+                }
+
+                let data = tree.data(id).expect("node should be in the tree");
+                label.clear();
+                write!(label, "{}", data)?;
+                out.push('(');
+                write_label(&label, out);
+                stack.push(None);
+                stack.extend(tree.children(id).iter().rev().map(|&child| Some(child)));
+            },
+            None => out.push(')'),
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `label` so that `Parser::parse_label` reads it back unchanged,
+/// quoting it when it could not be read back verbatim.
+fn write_label(label: &str, out: &mut String) {
+    let verbatim = !label.is_empty()
+        && label.trim() == label
+        && !label.starts_with('"')
+        && !label.contains(['(', ')', '\\']);
+
+    if verbatim {
+        out.push_str(label);
+        return;
+    }
+
+    out.push('"');
+    for c in label.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+struct Parser<'a, T, F> {
+    scanner: Scanner<'a>,
+    arena: Arena<T>,
+    parse_leaf: F,
+}
+
+impl<'a, T, F, E> Parser<'a, T, F>
+where
+    F: FnMut(&str) -> Result<T, E>,
+{
+    /// Parses a list and every list nested in it, returning the `NodeId` of
+    /// its node.
+    fn parse_tree(&mut self) -> Result<NodeId, ParseError<E>> {
+        let root = self.parse_label()?;
+        let mut open = vec![root];
+
+        while let Some(&id) = open.last() {
+            self.scanner.skip_whitespace();
+            match self.scanner.peek() {
+                Some('(') => {
+                    let child = self.parse_label()?;
+                    id.add_child(&mut self.arena, child);
+                    open.push(child);
+                },
+                Some(')') => {
+                    self.scanner.advance(1);
+                    open.pop();
+                },
+                _ => return Err(self.scanner.unexpected()),
+            }
+        }
+
+        Ok(root)
+    }
+
+    /// Parses the opening parenthesis and label of a list, and inserts its
+    /// node into the arena.
+    fn parse_label(&mut self) -> Result<NodeId, ParseError<E>> {
+        self.scanner.expect('(')?;
+        self.scanner.skip_whitespace();
+
+        let pos = self.scanner.pos();
+        let data = if self.scanner.eat('"') {
+            let label = self.parse_quoted()?;
+            (self.parse_leaf)(&label)
+        } else {
+            let rest = self.scanner.rest();
+            let len = rest.find(['(', ')']).unwrap_or(rest.len());
+            let label = rest[..len].trim_end();
+
+            if label.is_empty() {
+                return Err(self.scanner.unexpected());
+            }
+
+            self.scanner.advance(len);
+            (self.parse_leaf)(label)
+        };
+
+        let data = data.map_err(|error| ParseError::Leaf { pos, error })?;
+        Ok(self.arena.insert(data))
+    }
+
+    /// Parses the rest of a quoted label, after its opening quote, returning
+    /// it without quotes or escapes.
+    fn parse_quoted(&mut self) -> Result<String, ParseError<E>> {
+        let mut label = String::new();
+
+        loop {
+            match self.scanner.peek() {
+                Some('"') => {
+                    self.scanner.advance(1);
+                    return Ok(label);
+                },
+                Some('\\') => {
+                    self.scanner.advance(1);
+                    match self.scanner.peek() {
+                        Some(c @ ('"' | '\\')) => {
+                            label.push(c);
+                            self.scanner.advance(1);
+                        },
+                        _ => return Err(self.scanner.unexpected()),
+                    }
+                },
+                Some(c) => {
+                    label.push(c);
+                    self.scanner.advance(c.len_utf8());
+                },
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Arena<String>, ParseError<()>> {
+        Arena::from_sexpr(text, |label| Ok(label.to_string()))
+    }
+
+    #[test]
+    fn test_sexpr_round_trip() {
+        let text = "(binary (token 1) (token +) (unary (token -) (token 1)))";
+        let arena = parse(text).unwrap();
+        let root = arena.root().unwrap();
+
+        assert_eq!(arena.len(), 6);
+        assert_eq!(root.children(&arena).len(), 3);
+        assert_eq!(arena.to_sexpr(root), text);
+
+        // Whitespace between lists is not significant.
+        let spaced = parse("  ( binary\n  (token 1)\n  (token +)\n  (unary (token -)(token 1)))\n").unwrap();
+        assert_eq!(spaced, arena);
+    }
+
+    #[test]
+    fn test_sexpr_errors() {
+        assert_eq!(parse("(a (b)"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse("(a) (b)"), Err(ParseError::UnexpectedChar { pos: 4, found: '(' }));
+        assert_eq!(parse("(a (b) c)"), Err(ParseError::UnexpectedChar { pos: 7, found: 'c' }));
+        assert_eq!(parse("(a ( ))"), Err(ParseError::UnexpectedChar { pos: 5, found: ')' }));
+
+        let rejected = Arena::from_sexpr("(1 (x))", |label| label.parse::<u32>());
+        assert!(matches!(rejected, Err(ParseError::Leaf { pos: 4, .. })));
+    }
+
+    #[test]
+    fn test_sexpr_quoted_labels() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "group" => ["token (", "", " a ", "say \"hi\"", "\\", "token )"]);
+
+        let text = arena.to_sexpr(root);
+        assert_eq!(text, r#"(group ("token (") ("") (" a ") (say "hi") ("\\") ("token )"))"#);
+        let parsed = parse(&text).unwrap();
+        let labels: Vec<&str> = parsed.descendants(root).map(|id| parsed.data(id).unwrap().as_str()).collect();
+        assert_eq!(labels, ["group", "token (", "", " a ", "say \"hi\"", "\\", "token )"]);
+        assert_eq!(parsed.to_sexpr(root), text);
+
+        assert_eq!(parse(r#"("a\b")"#), Err(ParseError::UnexpectedChar { pos: 4, found: 'b' }));
+        assert_eq!(parse(r#"("a)"#), Err(ParseError::UnexpectedEnd));
+        assert_eq!(parse(r#"("a" b)"#), Err(ParseError::UnexpectedChar { pos: 5, found: 'b' }));
+    }
+
+    #[test]
+    fn test_sexpr_deep_chain() {
+        // Deep trees must not overflow the stack in either direction.
+        let depth = 200_000;
+        let text = "(x ".repeat(depth - 1) + "(x" + &")".repeat(depth);
+
+        let arena = parse(&text).unwrap();
+        assert_eq!(arena.len(), depth);
+        assert_eq!(arena.to_sexpr(arena.root().unwrap()), text);
+        assert_eq!(parse(&text[1..]), Err(ParseError::UnexpectedChar { pos: 0, found: 'x' }));
+    }
+}
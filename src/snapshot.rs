@@ -0,0 +1,149 @@
+use crate::Arena;
+use allocator_api2::alloc::{Allocator, Global};
+use core::fmt;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Takes a read-only snapshot of the `Arena` in constant time.
+    ///
+    /// Nodes are stored in reference counted chunks, which the snapshot
+    /// shares with the arena. The arena copies a chunk the first time it is
+    /// written to after a snapshot was taken, so the snapshot keeps seeing the
+    /// tree as it was, and copying is limited to the parts of the arena that
+    /// actually change. Snapshots can be sent to other threads and read while
+    /// the arena continues to be mutated.
+    ///
+    /// Digests, subtree sizes and the journal are not part of the snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// # use std::thread;
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "+" => ["1", "2"]);
+    /// let snapshot = arena.snapshot();
+    ///
+    /// let reader = thread::spawn(move || snapshot.arena().to_sexpr(root));
+    ///
+    /// let three = arena.insert("3");
+    /// root.add_child(arena, three);
+    /// let one = root.children(arena)[0];
+    /// one.set_data(arena, "0");
+    ///
+    /// assert_eq!(reader.join().unwrap(), "(+ (1) (2))");
+    /// assert_eq!(arena.to_sexpr(root), "(+ (0) (2) (3))");
+    /// ```
+    pub fn snapshot(&mut self) -> ArenaSnapshot<T, A>
+    where
+        T: Clone,
+    {
+        ArenaSnapshot {
+            arena: Arena {
+                nodes: self.nodes.share(Clone::clone),
+                root: self.root,
+                ..Arena::empty_in(self.nodes.allocator().clone())
+            },
+        }
+    }
+}
+
+/// A read-only view of an `Arena` at the time it was taken with
+/// `Arena::snapshot`.
+pub struct ArenaSnapshot<T, A: Allocator = Global> {
+    arena: Arena<T, A>,
+}
+
+impl<T, A> ArenaSnapshot<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns a reference to the `Arena` as it was when the snapshot was
+    /// taken.
+    pub fn arena(&self) -> &Arena<T, A> {
+        &self.arena
+    }
+
+    /// Consumes the snapshot, returning an `Arena` which can be mutated again.
+    ///
+    /// The returned arena still shares its storage with the arena the
+    /// snapshot was taken from, and with any other snapshots, copying chunks
+    /// as they are written to.
+    pub fn into_arena(self) -> Arena<T, A> {
+        self.arena
+    }
+}
+
+impl<T, A> Clone for ArenaSnapshot<T, A>
+where
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            arena: Arena {
+                nodes: self.arena.nodes.clone(),
+                root: self.arena.root,
+                ..Arena::empty_in(self.arena.nodes.allocator().clone())
+            },
+        }
+    }
+}
+
+impl<T, A> fmt::Debug for ArenaSnapshot<T, A>
+where
+    T: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaSnapshot")
+            .field("arena", &self.arena)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NodeId;
+
+    #[test]
+    fn test_snapshot() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, 0 => [1, 2, 3]);
+        for i in 4..300 {
+            let id = arena.insert(i);
+            NodeId(i / 4).add_child(arena, id);
+        }
+        arena.enable_digests();
+
+        let before = arena.nodes();
+        let digest = arena.subtree_digest(root);
+        let first = arena.snapshot();
+
+        NodeId(5).set_data(arena, 500);
+        let last = arena.insert(300);
+        root.add_child(arena, last);
+        let second = arena.snapshot();
+        let copy = second.clone();
+
+        NodeId(299).detach(arena);
+
+        assert_eq!(first.arena().nodes(), before);
+        assert_eq!(first.arena().subtree_hash(root), digest);
+        assert_eq!(second.arena().len(), 301);
+        assert_eq!(second.arena().node_at(NodeId(5)).unwrap().data(), &500);
+        assert_eq!(second.arena().node_at(NodeId(299)).unwrap().parent(), Some(NodeId(74)));
+        assert_eq!(NodeId(299).parent(arena), None);
+        assert_eq!(arena.subtree_digest(root), arena.subtree_hash(root));
+
+        // Snapshots can be turned back into arenas, without affecting others.
+        let mut fork = first.into_arena();
+        NodeId(1).set_data(&mut fork, 100);
+        fork.insert(1000);
+        assert_eq!(fork.len(), 301);
+        assert_eq!(copy.arena(), second.arena());
+        assert_eq!(arena.node_at(NodeId(1)).unwrap().data(), &1);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{Arena, Node, NodeId, Tree, TreeMut};
+use alloc::vec::Vec;
+use core::mem;
+use core::ops::Range;
+
+/// An arena storing every field of its nodes in a separate vector.
+///
+/// `Arena` keeps the data, parent and children of a node together, so
+/// walking up the parents of a node also loads the data of every node on the
+/// way. A `SoaArena` (for struct of arrays) stores parents, data and children
+/// apart from each other, which keeps such walks within the parent vector.
+/// The children of all nodes share a single vector, and every node keeps the
+/// range of its own children within it.
+///
+/// A `SoaArena` is read and changed through the `Tree` and `TreeMut` traits,
+/// so the methods of `NodeId` work on it as they do on an `Arena`. Arenas can
+/// be converted to and from the other layout, keeping every `NodeId`.
+///
+/// A `SoaArena` does not store `Node` values, so instead of
+/// `Arena::node_at` its data is read with `Tree::data` and changed with
+/// `SoaArena::data_mut`, while `SoaArena::nodes` builds the nodes on
+/// request. It keeps no journal, digests, cached sizes or listeners, so the
+/// methods of `Arena` built on them are only offered there: convert with
+/// `SoaArena::into_arena` to use them. Nodes are never removed from either
+/// layout, only detached.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena, SoaArena, Tree, TreeMut};
+/// let mut arena = Arena::new();
+/// let root = tree!(&mut arena, "+" => ["1", "*" => ["2", "3"]]);
+///
+/// let mut soa = SoaArena::from(arena);
+/// let times = root.children(&soa)[1];
+/// let four = soa.insert("4");
+/// times.add_child(&mut soa, four);
+///
+/// let two = times.children(&soa)[0];
+/// assert_eq!(soa.ancestors(two).collect::<Vec<_>>(), vec![times, root]);
+/// assert_eq!(soa.to_sexpr(root), "(+ (1) (* (2) (3) (4)))");
+/// ```
+pub struct SoaArena<T> {
+    data: Vec<T>,
+    parents: Vec<Option<NodeId>>,
+    ranges: Vec<Range<usize>>,
+    children: Vec<NodeId>,
+    live: usize,
+    root: Option<NodeId>,
+}
+
+impl<T> SoaArena<T> {
+    /// Creates a new empty `SoaArena`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a mutable reference to the data of the node at `id`, or `None`
+    /// if it is not in the arena.
+    pub fn data_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.data.get_mut(id.index())
+    }
+
+    /// Returns a new vector of all the nodes present in the `SoaArena`.
+    pub fn nodes(&self) -> Vec<Node<T>>
+    where
+        T: Clone,
+    {
+        self.iter()
+            .map(|(id, data)| Node::with(id, data.clone(), self.parents[id.index()], self.children(id).to_vec()))
+            .collect()
+    }
+
+    /// Converts the arena back into an `Arena` with the same `NodeId`s.
+    pub fn into_arena(self) -> Arena<T> {
+        let Self { data, parents, ranges, children, root, .. } = self;
+
+        let nodes = data.into_iter()
+            .zip(parents)
+            .zip(ranges)
+            .enumerate()
+            .map(|(index, ((data, parent), range))| {
+                Node::with(NodeId(index), data, parent, children[range].to_vec())
+            })
+            .collect();
+
+        Arena { nodes, root, ..Arena::new() }
+    }
+
+    /// Rebuilds the children vector without the ranges left unused by
+    /// children which have been moved.
+    fn compact(&mut self) {
+        let mut children = Vec::with_capacity(self.live);
+        for range in &mut self.ranges {
+            let start = children.len();
+            children.extend_from_slice(&self.children[range.clone()]);
+            *range = start..children.len();
+        }
+        self.children = children;
+    }
+}
+
+impl<T> Tree for SoaArena<T> {
+    type Data = T;
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn root(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    fn data(&self, id: NodeId) -> Option<&T> {
+        self.data.get(id.index())
+    }
+
+    fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.parents[id.index()]
+    }
+
+    fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.children[self.ranges[id.index()].clone()]
+    }
+}
+
+impl<T> TreeMut for SoaArena<T> {
+    fn push(&mut self, data: T) -> NodeId {
+        let id = NodeId(self.data.len());
+        let end = self.children.len();
+        self.data.push(data);
+        self.parents.push(None);
+        self.ranges.push(end..end);
+        id
+    }
+
+    fn set_root(&mut self, root: Option<NodeId>) {
+        self.root = root;
+    }
+
+    fn set_data(&mut self, id: NodeId, data: T) -> T {
+        mem::replace(&mut self.data[id.index()], data)
+    }
+
+    fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        self.parents[id.index()] = parent;
+    }
+
+    /// The children of a node can only grow in place while they are at the
+    /// end of the shared children vector. Otherwise they are moved there
+    /// first, and the vector is compacted once most of it is left unused.
+    fn insert_child(&mut self, parent: NodeId, index: usize, child: NodeId) {
+        let range = self.ranges[parent.index()].clone();
+        assert!(index <= range.len(), "child index should be in bounds");
+
+        if range.end != self.children.len() {
+            if self.children.len() >= 2 * self.live + 64 {
+                self.compact();
+            }
+
+            let start = self.children.len();
+            self.children.extend_from_within(self.ranges[parent.index()].clone());
+            self.ranges[parent.index()] = start..self.children.len();
+        }
+
+        let range = &mut self.ranges[parent.index()];
+        self.children.insert(range.start + index, child);
+        range.end += 1;
+        self.live += 1;
+        self.parents[child.index()] = Some(parent);
+    }
+
+    fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Option<usize> {
+        let range = self.ranges[parent.index()].clone();
+        let siblings = &mut self.children[range];
+        let index = siblings.iter().position(|&other| other == child)?;
+
+        siblings.copy_within(index + 1.., index);
+        self.ranges[parent.index()].end -= 1;
+        self.live -= 1;
+        Some(index)
+    }
+}
+
+impl<T> Default for SoaArena<T> {
+    fn default() -> Self {
+        Self {
+            data: Vec::new(),
+            parents: Vec::new(),
+            ranges: Vec::new(),
+            children: Vec::new(),
+            live: 0,
+            root: None,
+        }
+    }
+}
+
+impl<T> From<Arena<T>> for SoaArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        let mut soa = Self { root: arena.root, ..Self::default() };
+
+        for node in arena.nodes {
+            let start = soa.children.len();
+            soa.live += node.children.len();
+            soa.children.extend(node.children);
+            soa.ranges.push(start..soa.children.len());
+            soa.parents.push(node.parent);
+            soa.data.push(node.data);
+        }
+
+        soa
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soa_arena() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, 1 => [2, 3 => [4]]);
+        let mut soa = SoaArena::from(arena.snapshot().into_arena());
+
+        // Mirror every change on both layouts.
+        let mut nodes = vec![root];
+        for i in 5..300 {
+            let parent = nodes[(i * 7) % nodes.len()];
+            let id = arena.insert(i);
+            assert_eq!(soa.insert(i), id);
+
+            let index = (i * 3) % (parent.children(arena).len() + 1);
+            parent.insert_child(arena, index, id);
+            parent.insert_child(&mut soa, index, id);
+            nodes.push(id);
+
+            if i % 5 == 0 {
+                let other = nodes[(i * 11) % nodes.len()];
+                other.detach(arena);
+                other.detach(&mut soa);
+            }
+        }
+
+        for index in 0..arena.len() {
+            let id = NodeId(index);
+            assert_eq!(id.parent(&soa), id.parent(arena));
+            assert_eq!(id.children(&soa), id.children(arena));
+            assert_eq!(soa.depth(id), arena.depth(id));
+            assert_eq!(soa.lca(id, NodeId(index / 2)), arena.lca(id, NodeId(index / 2)));
+        }
+        assert_eq!(soa.live, soa.ranges.iter().map(|range| range.len()).sum::<usize>());
+        assert!(soa.children.len() < 2 * soa.live + 64);
+
+        root.set_data(&mut soa, 0);
+        root.set_data(arena, 0);
+        assert!(soa.iter().eq(arena.iter()));
+        assert_eq!(soa.nodes(), arena.nodes());
+        assert!(soa.contains(NodeId(arena.len() - 1)) && !soa.contains(NodeId(arena.len())));
+        assert_eq!(soa.to_sexpr(root), arena.to_sexpr(root));
+        assert_eq!(&soa.into_arena(), arena);
+        assert_eq!(SoaArena::new().into_arena(), Arena::<i32>::new());
+    }
+}
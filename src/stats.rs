@@ -0,0 +1,278 @@
+use crate::{Arena, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::mem;
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the number of nodes in the subtree at `id`, including itself.
+    ///
+    /// If cached sizes have been enabled with `Arena::enable_subtree_sizes`,
+    /// this is a constant time lookup. Otherwise, the subtree is traversed.
+    pub fn subtree_size(&self, id: NodeId) -> usize {
+        match &self.sizes {
+            Some(sizes) => sizes[id.index()],
+            None => self.preorder(id).count(),
+        }
+    }
+
+    /// Returns the height of the subtree at `id`, which is the number of
+    /// edges on the longest path from it down to a leaf.
+    pub fn height(&self, id: NodeId) -> usize {
+        self.preorder(id).map(|(_, depth)| depth).max().unwrap_or(0)
+    }
+
+    /// Returns the number of nodes without children in the subtree at `id`.
+    pub fn leaf_count(&self, id: NodeId) -> usize {
+        self.preorder(id)
+            .filter(|&(id, _)| self.get(id).children().is_empty())
+            .count()
+    }
+
+    /// Collects statistics about the shape of every node in the `Arena`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+    /// arena.insert("5");
+    ///
+    /// let stats = arena.stats();
+    /// assert_eq!(stats.node_count, 5);
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.branching, vec![3, 1, 1]);
+    /// assert_eq!(stats.orphan_count, 1);
+    /// ```
+    pub fn stats(&self) -> ArenaStats {
+        let mut stats = ArenaStats {
+            node_count: self.len(),
+            ..ArenaStats::default()
+        };
+        let mut depths: Vec<Option<usize>> = vec![None; self.len()];
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let children = &node.children;
+
+            if stats.branching.len() <= children.len() {
+                stats.branching.resize(children.len() + 1, 0);
+            }
+            stats.branching[children.len()] += 1;
+            stats.children_bytes += children.capacity() * mem::size_of::<NodeId>();
+
+            if node.parent().is_none() && self.root != Some(NodeId(index)) {
+                stats.orphan_count += 1;
+            }
+
+            // Walk up to the closest ancestor with a known depth, then fill
+            // in the depths on the way back down.
+            let mut path = vec![NodeId(index)];
+            while let Some(parent) = self.get(path[path.len() - 1]).parent() {
+                if depths[parent.index()].is_some() || path.len() > self.len() {
+                    path.push(parent);
+                    break;
+                }
+                path.push(parent);
+            }
+
+            let top = path.pop().expect("path should start with the node itself");
+            let mut depth = depths[top.index()].unwrap_or(0);
+            depths[top.index()] = Some(depth);
+
+            for id in path.into_iter().rev() {
+                depth += 1;
+                depths[id.index()] = Some(depth);
+            }
+
+            stats.max_depth = stats.max_depth.max(depths[index].unwrap_or(0));
+        }
+
+        stats
+    }
+
+    /// Starts caching the size of every subtree in the `Arena`.
+    ///
+    /// Once enabled, sizes are computed for every node straight away, and are
+    /// updated for the affected node and its ancestors whenever the arena is
+    /// mutated through `NodeId`. This makes `Arena::subtree_size` a constant
+    /// time lookup, at the cost of walking up to the root on every
+    /// `NodeId::add_child`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// arena.enable_subtree_sizes();
+    ///
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+    /// assert_eq!(arena.subtree_size(root), 4);
+    ///
+    /// let str_3 = root.children(arena)[1];
+    /// str_3.detach(arena);
+    /// assert_eq!(arena.subtree_size(root), 2);
+    /// assert_eq!(arena.subtree_size(str_3), 2);
+    /// ```
+    pub fn enable_subtree_sizes(&mut self) {
+        if self.sizes.is_some() {
+            return;
+        }
+
+        let mut sizes = vec![None; self.nodes.len()];
+
+        for index in 0..self.nodes.len() {
+            let mut stack = vec![(NodeId(index), false)];
+
+            while let Some((id, visited)) = stack.pop() {
+                if sizes[id.index()].is_some() {
+                    continue;
+                }
+
+                let node = self.get(id);
+
+                if visited {
+                    let size = 1 + node.children().iter()
+                        .map(|child| sizes[child.index()].expect("child size should be computed"))
+                        .sum::<usize>();
+                    sizes[id.index()] = Some(size);
+                } else {
+                    stack.push((id, true));
+                    stack.extend(node.children().iter().rev().map(|&child| (child, false)));
+                }
+            }
+        }
+
+        self.sizes = Some(sizes.into_iter().flatten().collect());
+    }
+
+    /// Stops caching the size of every subtree in the `Arena`.
+    pub fn disable_subtree_sizes(&mut self) {
+        self.sizes = None;
+    }
+
+    /// Returns `true` if the `Arena` caches the size of every subtree.
+    pub fn has_subtree_sizes(&self) -> bool {
+        self.sizes.is_some()
+    }
+
+    /// Recomputes the cached size of the node at `id` from its children, and
+    /// applies the difference to all of its ancestors.
+    pub(crate) fn refresh_sizes(&mut self, id: NodeId) {
+        let sizes = match &mut self.sizes {
+            Some(sizes) => sizes,
+            None => return,
+        };
+
+        let node = &self.nodes[id.index()];
+        let old = sizes[id.index()];
+        let new = 1 + node.children().iter()
+            .map(|child| sizes[child.index()])
+            .sum::<usize>();
+
+        if old == new {
+            return;
+        }
+
+        sizes[id.index()] = new;
+        let mut current = node.parent();
+
+        while let Some(ancestor) = current {
+            sizes[ancestor.index()] = sizes[ancestor.index()] + new - old;
+            current = self.nodes[ancestor.index()].parent();
+        }
+    }
+}
+
+/// Statistics about the shape of an `Arena`, returned by `Arena::stats`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ArenaStats {
+    /// The number of nodes in the arena.
+    pub node_count: usize,
+    /// The greatest depth of any node, counted in parent links.
+    pub max_depth: usize,
+    /// The number of nodes with each number of children, indexed by the
+    /// number of children.
+    pub branching: Vec<usize>,
+    /// The number of nodes without a parent, other than the root.
+    pub orphan_count: usize,
+    /// The number of bytes allocated for the children lists of every node.
+    pub children_bytes: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_subtree_statistics() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+        let str_2 = root.children(arena)[0];
+        let str_4 = root.children(arena)[2];
+
+        assert_eq!(arena.subtree_size(root), 8);
+        assert_eq!(arena.subtree_size(str_4), 3);
+        assert_eq!(arena.height(root), 3);
+        assert_eq!(arena.height(str_2), 1);
+        assert_eq!(arena.leaf_count(root), 4);
+        assert_eq!(arena.leaf_count(str_4), 1);
+
+        let stats = arena.stats();
+        assert_eq!(stats.node_count, 8);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.branching, vec![4, 2, 1, 1]);
+        assert_eq!(stats.orphan_count, 0);
+
+        // Cached sizes agree with traversing the tree after every mutation.
+        arena.enable_subtree_sizes();
+        let check = |arena: &Arena<i32>| {
+            for index in 0..arena.len() {
+                let id = NodeId(index);
+                assert_eq!(arena.subtree_size(id), arena.preorder(id).count());
+            }
+        };
+
+        let str_9 = tree!(arena, 9 => [10]);
+        check(arena);
+        str_2.add_child(arena, str_9);
+        check(arena);
+        str_4.detach(arena);
+        check(arena);
+        str_9.insert_child(arena, 0, str_4);
+        check(arena);
+        assert_eq!(arena.subtree_size(root), 10);
+    }
+
+    #[test]
+    fn test_stats_edge_cases() {
+        // An empty arena has no nodes of any shape.
+        let arena = &mut Arena::<i32>::new();
+        assert_eq!(arena.stats(), ArenaStats::default());
+        arena.enable_subtree_sizes();
+        assert!(arena.has_subtree_sizes());
+
+        // Detached subtrees count their top node as an orphan, and their
+        // depths restart from it.
+        let root = fixtures::sample(arena);
+        let [two, four] = [2, 4].map(|label| fixtures::find(arena, label));
+        four.detach(arena);
+        two.detach(arena);
+        let stats = arena.stats();
+        assert_eq!(stats.orphan_count, 2);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(arena.subtree_size(root), 2);
+
+        // Sizes changed while caching is disabled are recomputed when it is
+        // enabled again.
+        arena.disable_subtree_sizes();
+        root.add_child(arena, four);
+        arena.enable_subtree_sizes();
+        assert_eq!(arena.subtree_size(root), 5);
+        assert_eq!(arena.subtree_size(two), 3);
+    }
+}
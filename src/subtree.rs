@@ -0,0 +1,323 @@
+use crate::collections::FnvHasher;
+use crate::{Arena, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::Allocator;
+use core::hash::{Hash, Hasher};
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Compares the subtree at `root` with the subtree at `other_root` in
+    /// `other`.
+    ///
+    /// Two subtrees are equal if their roots hold equal data and have the
+    /// same number of children, with each pair of children being equal in
+    /// turn. The `NodeId` indices of the nodes are ignored, so trees built by
+    /// inserting their nodes in a different order still compare equal.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let a = &mut Arena::new();
+    /// let a_root = a.insert("+");
+    /// let a_one = a.insert("1");
+    /// let a_two = a.insert("2");
+    /// a_root.add_child(a, a_one).add_child(a, a_two);
+    ///
+    /// // Insert the leaves before the root this time.
+    /// let b = &mut Arena::new();
+    /// let b_one = b.insert("1");
+    /// let b_two = b.insert("2");
+    /// let b_root = b.insert("+");
+    /// b_root.add_child(b, b_one).add_child(b, b_two);
+    ///
+    /// assert_ne!(a, b);
+    /// assert!(a.subtree_eq(a_root, b, b_root));
+    /// assert_eq!(a.subtree_hash(a_root), b.subtree_hash(b_root));
+    /// ```
+    pub fn subtree_eq<U>(&self, root: NodeId, other: &Arena<U>, other_root: NodeId) -> bool
+    where
+        T: PartialEq<U>,
+    {
+        let mut stack = vec![(root, other_root)];
+
+        while let Some((a, b)) = stack.pop() {
+            let a = self.get(a);
+            let b = other.get(b);
+
+            if a.data() != b.data() || a.children().len() != b.children().len() {
+                return false;
+            }
+
+            stack.extend(a.children().iter().copied().zip(b.children().iter().copied()));
+        }
+
+        true
+    }
+
+    /// Computes a hash of the shape and data of the subtree at `root`.
+    ///
+    /// Subtrees that compare equal with `Arena::subtree_eq` always have the
+    /// same hash, regardless of which arena they live in or the order their
+    /// nodes were inserted in. The hash does not depend on the `std` feature.
+    pub fn subtree_hash(&self, root: NodeId) -> u64
+    where
+        T: Hash,
+    {
+        let mut digests = Vec::new();
+        let mut stack = vec![(root, false)];
+
+        while let Some((id, visited)) = stack.pop() {
+            let node = self.get(id);
+
+            if visited {
+                let start = digests.len() - node.children().len();
+                let digest = combine(node.data(), &digests[start..]);
+                digests.truncate(start);
+                digests.push(digest);
+            } else {
+                stack.push((id, true));
+                stack.extend(node.children().iter().rev().map(|&child| (child, false)));
+            }
+        }
+
+        digests[0]
+    }
+
+    /// Starts keeping a digest of every subtree in the `Arena`.
+    ///
+    /// The digest of a node combines the hash of its data with the digests of
+    /// its children, and is equal to `Arena::subtree_hash` of that node. Once
+    /// enabled, digests are computed for every node straight away, and are
+    /// updated for the affected node and its ancestors whenever the arena is
+    /// mutated through `NodeId`.
+    ///
+    /// Updates follow parent links, so a node whose parent was changed with
+    /// `NodeId::set_parent` without also being added to its new parent's
+    /// children will not keep the digests of either parent up to date.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::Arena;
+    /// let arena = &mut Arena::new();
+    /// arena.enable_digests();
+    ///
+    /// let plus = arena.insert("+");
+    /// let one = arena.insert("1");
+    /// let two = arena.insert("2");
+    /// plus.add_child(arena, one).add_child(arena, two);
+    ///
+    /// let before = arena.subtree_digest(plus);
+    /// two.set_data(arena, "3");
+    ///
+    /// assert_ne!(arena.subtree_digest(plus), before);
+    /// assert_eq!(arena.subtree_digest(plus), arena.subtree_hash(plus));
+    /// ```
+    pub fn enable_digests(&mut self)
+    where
+        T: Hash,
+    {
+        if self.digests.is_some() {
+            return;
+        }
+
+        let mut values = vec![None; self.nodes.len()];
+
+        for index in 0..self.nodes.len() {
+            let mut stack = vec![(NodeId(index), false)];
+
+            while let Some((id, visited)) = stack.pop() {
+                if values[id.index()].is_some() {
+                    continue;
+                }
+
+                let node = self.get(id);
+
+                if visited {
+                    let children: Vec<u64> = node.children().iter()
+                        .map(|child| values[child.index()].expect("child digest should be computed"))
+                        .collect();
+                    values[id.index()] = Some(combine(node.data(), &children));
+                } else {
+                    stack.push((id, true));
+                    stack.extend(node.children().iter().rev().map(|&child| (child, false)));
+                }
+            }
+        }
+
+        self.digests = Some(Digests {
+            combine: combine::<T, u64>,
+            values: values.into_iter().flatten().collect(),
+        });
+    }
+
+    /// Stops keeping digests of the subtrees in the `Arena`.
+    pub fn disable_digests(&mut self) {
+        self.digests = None;
+    }
+
+    /// Returns `true` if the `Arena` keeps a digest of every subtree.
+    pub fn has_digests(&self) -> bool {
+        self.digests.is_some()
+    }
+
+    /// Returns the digest of the subtree at `id`.
+    ///
+    /// If digests have been enabled with `Arena::enable_digests`, this is a
+    /// constant time lookup. Otherwise, the digest is computed from scratch
+    /// with `Arena::subtree_hash`.
+    pub fn subtree_digest(&self, id: NodeId) -> u64
+    where
+        T: Hash,
+    {
+        match &self.digests {
+            Some(digests) => digests.values[id.index()],
+            None => self.subtree_hash(id),
+        }
+    }
+
+    /// Recomputes the digest of the node at `id` and its ancestors, stopping
+    /// early once a digest is found to be unchanged.
+    pub(crate) fn refresh_digests(&mut self, id: NodeId) {
+        let digests = match &mut self.digests {
+            Some(digests) => digests,
+            None => return,
+        };
+
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            let node = &self.nodes[id.index()];
+            let children: Vec<u64> = node.children().iter()
+                .map(|child| digests.values[child.index()])
+                .collect();
+            let digest = (digests.combine)(node.data(), &children);
+
+            if digests.values[id.index()] == digest {
+                break;
+            }
+
+            digests.values[id.index()] = digest;
+            current = node.parent();
+        }
+    }
+}
+
+/// The digests of every subtree in an `Arena`, kept up to date as it is
+/// mutated.
+///
+/// The function used to combine digests is captured when digests are enabled,
+/// so that they can be updated without requiring `T: Hash` everywhere else.
+pub(crate) struct Digests<T> {
+    combine: fn(&T, &[u64]) -> u64,
+    values: Vec<u64>,
+}
+
+impl<T> Digests<T> {
+    /// Records the digest of a newly inserted node.
+    pub(crate) fn push(&mut self, data: &T) {
+        self.values.push((self.combine)(data, &[]));
+    }
+
+    /// Forgets the digest of the last node, after it has been removed.
+    pub(crate) fn pop(&mut self) {
+        self.values.pop();
+    }
+}
+
+/// Hashes a node's data together with a value for each of its children,
+/// such as their digests or their `NodeId`s.
+pub(crate) fn combine<T, C>(data: &T, children: &[C]) -> u64
+where
+    T: Hash,
+    C: Hash,
+{
+    let mut hasher = FnvHasher::new();
+    data.hash(&mut hasher);
+    children.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subtree_eq() {
+        let a = &mut Arena::new();
+        let a_root = tree!(a, "1" => ["2", "3" => ["4"]]);
+
+        let b = &mut Arena::new();
+        let b_four = b.insert("4");
+        let b_three = b.insert("3");
+        let b_two = b.insert("2");
+        let b_root = b.insert("1");
+        b_three.add_child(b, b_four);
+        b_root.add_child(b, b_two).add_child(b, b_three);
+
+        assert!(a.subtree_eq(a_root, b, b_root));
+        assert!(b.subtree_eq(b_three, a, a_root.children(a)[1]));
+        assert_eq!(a.subtree_hash(a_root), b.subtree_hash(b_root));
+
+        // Swapping the order of the children changes the tree.
+        let c = &mut Arena::new();
+        let c_root = tree!(c, "1" => ["3" => ["4"], "2"]);
+        assert!(!a.subtree_eq(a_root, c, c_root));
+        assert_ne!(a.subtree_hash(a_root), c.subtree_hash(c_root));
+
+        // Moving a leaf to a different level changes the tree.
+        let d = &mut Arena::new();
+        let d_root = tree!(d, "1" => ["2", "3", "4"]);
+        assert!(!a.subtree_eq(a_root, d, d_root));
+        assert_ne!(a.subtree_hash(a_root), d.subtree_hash(d_root));
+    }
+
+    #[test]
+    fn test_subtree_digest() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+        arena.enable_digests();
+
+        let check = |arena: &Arena<&str>| {
+            for index in 0..arena.len() {
+                let id = NodeId(index);
+                assert_eq!(arena.subtree_digest(id), arena.subtree_hash(id));
+            }
+        };
+        check(arena);
+
+        // Digests are the same with and without the `std` feature.
+        #[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+        assert_eq!(arena.subtree_digest(root), 13_793_633_807_945_324_870);
+
+        let str_2 = root.children(arena)[0];
+        let str_3 = root.children(arena)[1];
+        let str_4 = str_3.children(arena)[0];
+        let before = arena.subtree_digest(root);
+
+        let str_5 = arena.insert("5");
+        str_4.add_child(arena, str_5);
+        check(arena);
+
+        str_3.detach(arena);
+        str_2.insert_child(arena, 0, str_3);
+        check(arena);
+
+        str_5.set_data(arena, "6");
+        check(arena);
+
+        // Undoing every change restores the original digest.
+        str_5.detach(arena);
+        str_3.detach(arena);
+        root.add_child(arena, str_3);
+        assert_eq!(arena.subtree_digest(root), before);
+
+        // Digests are kept for nodes that are not yet part of the tree.
+        let orphan = tree!(arena, "1" => ["2", "3" => ["4"]]);
+        assert_eq!(arena.subtree_digest(orphan), before);
+    }
+}
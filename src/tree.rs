@@ -0,0 +1,211 @@
+use crate::{lca, sexpr, NodeId};
+use alloc::string::String;
+use alloc::vec;
+use core::fmt::Display;
+
+/// Read access to a tree of nodes identified by `NodeId`s.
+///
+/// `Tree` is implemented by `Arena` as well as by its other layouts, such as
+/// `SoaArena` and `FrozenTree`, so that code walking a tree does not depend
+/// on how it is stored. The methods of `NodeId` reading a tree, such as
+/// `NodeId::parent` and `NodeId::children`, accept any `Tree`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use isaac::{tree, Arena, NodeId, SoaArena, Tree};
+/// fn leaves<A: Tree>(tree: &A, root: NodeId) -> usize {
+///     tree.descendants(root).filter(|&id| id.children(tree).is_empty()).count()
+/// }
+///
+/// let mut arena = Arena::new();
+/// let root = tree!(&mut arena, "+" => ["1", "*" => ["2", "3"]]);
+/// assert_eq!(leaves(&arena, root), 3);
+///
+/// let soa = SoaArena::from(arena);
+/// assert_eq!(leaves(&soa, root), 3);
+/// assert_eq!(soa.to_sexpr(root), "(+ (1) (* (2) (3)))");
+/// ```
+pub trait Tree {
+    /// The data stored in every node.
+    type Data;
+
+    /// Returns the number of nodes in the tree.
+    fn len(&self) -> usize;
+
+    /// Returns the root `NodeId` of the tree.
+    fn root(&self) -> Option<NodeId>;
+
+    /// Returns a reference to the data of the node at `id`, or `None` if it
+    /// is not in the tree.
+    fn data(&self, id: NodeId) -> Option<&Self::Data>;
+
+    /// Returns the parent of the node at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not in the tree.
+    fn parent(&self, id: NodeId) -> Option<NodeId>;
+
+    /// Returns the children of the node at `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not in the tree.
+    fn children(&self, id: NodeId) -> &[NodeId];
+
+    /// Returns `true` if the tree has no nodes.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the node at `id` is in the tree.
+    fn contains(&self, id: NodeId) -> bool {
+        id.index() < self.len()
+    }
+
+    /// Returns an iterator over the `NodeId` and data of every node in the
+    /// tree, in index order.
+    fn iter(&self) -> impl Iterator<Item = (NodeId, &Self::Data)> + '_ {
+        (0..self.len()).map(move |index| {
+            let id = NodeId(index);
+            (id, self.data(id).expect("node should be in the tree"))
+        })
+    }
+
+    /// Returns an iterator over the ancestors of the node at `id`, from its
+    /// parent up to the top of its tree.
+    fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        core::iter::successors(self.parent(id), move |&id| self.parent(id))
+    }
+
+    /// Returns an iterator over `root` and its descendants, in pre-order.
+    fn descendants(&self, root: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let mut stack = vec![root];
+        core::iter::from_fn(move || {
+            let id = stack.pop()?;
+            stack.extend(self.children(id).iter().rev());
+            Some(id)
+        })
+    }
+
+    /// Returns the depth of the node at `id`, like `Arena::depth`.
+    fn depth(&self, id: NodeId) -> usize {
+        lca::depth(self, id)
+    }
+
+    /// Returns the lowest common ancestor of two nodes, like `Arena::lca`.
+    fn lca(&self, a: NodeId, b: NodeId) -> Option<NodeId> {
+        lca::lca(self, a, b)
+    }
+
+    /// Renders the subtree starting at `root` as an S-expression, like
+    /// `Arena::to_sexpr`.
+    fn to_sexpr(&self, root: NodeId) -> String
+    where
+        Self::Data: Display,
+    {
+        sexpr::to_sexpr(self, root)
+    }
+}
+
+/// Write access to a tree of nodes identified by `NodeId`s.
+///
+/// The methods of `NodeId` changing a tree, such as `NodeId::add_child` and
+/// `NodeId::detach`, accept any `TreeMut`. As with `Arena`, they only keep
+/// both sides of a parent link in sync when a child is added or detached.
+pub trait TreeMut: Tree {
+    /// Appends a new node to the tree without making it the root, and
+    /// returns its `NodeId`.
+    fn push(&mut self, data: Self::Data) -> NodeId;
+
+    /// Sets a new root for the tree.
+    fn set_root(&mut self, root: Option<NodeId>);
+
+    /// Replaces the data of the node at `id`, returning the previous data.
+    fn set_data(&mut self, id: NodeId, data: Self::Data) -> Self::Data;
+
+    /// Sets a new parent for the node at `id`, without adding it to the
+    /// children of the parent.
+    fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>);
+
+    /// Inserts `child` into the children of `parent` at `index` and sets
+    /// `parent` as its parent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of children.
+    fn insert_child(&mut self, parent: NodeId, index: usize, child: NodeId);
+
+    /// Removes `child` from the children of `parent` without clearing its
+    /// parent, returning the position it was found at.
+    fn remove_child(&mut self, parent: NodeId, child: NodeId) -> Option<usize>;
+
+    /// Inserts a new value into the tree and returns its `NodeId`. The first
+    /// node inserted becomes the root.
+    fn insert(&mut self, data: Self::Data) -> NodeId {
+        let id = self.push(data);
+        if id.index() == 0 {
+            self.set_root(Some(id));
+        }
+        id
+    }
+
+    /// Appends `child` to the children of `parent` and sets `parent` as its
+    /// parent.
+    fn add_child(&mut self, parent: NodeId, child: NodeId) {
+        let index = self.children(parent).len();
+        self.insert_child(parent, index, child);
+    }
+
+    /// Removes the node at `id` from the children of its parent and clears
+    /// its parent.
+    fn detach(&mut self, id: NodeId) {
+        if let Some(parent) = self.parent(id) {
+            self.remove_child(parent, id);
+            self.set_parent(id, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arena, Selector, SoaArena};
+
+    /// Builds `(1 (2) (3 (4)))` through `TreeMut` alone.
+    fn build<A: TreeMut<Data = i32>>(tree: &mut A) -> NodeId {
+        let ids: Vec<NodeId> = (1..=4).map(|data| tree.insert(data)).collect();
+        tree.add_child(ids[0], ids[2]);
+        tree.insert_child(ids[0], 0, ids[1]);
+        tree.add_child(ids[2], ids[3]);
+        ids[0]
+    }
+
+    #[test]
+    fn test_tree_layouts() {
+        let arena = &mut Arena::new();
+        arena.enable_journal();
+        let root = build(arena);
+        let mut soa = SoaArena::new();
+        assert_eq!(build(&mut soa), root);
+
+        let names = ["even", "odd"];
+        let selector = Selector::parse("odd > odd > even", move |&data: &i32| names[data as usize % 2]).unwrap();
+        for index in 0..4 {
+            let id = NodeId(index);
+            assert_eq!(selector.matches(arena, root, id), selector.matches(&soa, root, id));
+            assert_eq!(soa.ancestors(id).collect::<Vec<_>>(), arena.ancestors(id).collect::<Vec<_>>());
+        }
+        assert_eq!(soa.descendants(root).collect::<Vec<_>>(), [0, 1, 2, 3].map(NodeId));
+        assert_eq!(soa.to_sexpr(root), "(1 (2) (3 (4)))");
+
+        // Changes made through `TreeMut` are still journaled as single steps.
+        let three = NodeId(2);
+        three.detach(arena);
+        three.detach(&mut soa);
+        assert_eq!(arena.to_sexpr(root), soa.to_sexpr(root));
+        assert!(arena.undo());
+        assert_eq!(arena.to_sexpr(root), "(1 (2) (3 (4)))");
+    }
+}
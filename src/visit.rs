@@ -0,0 +1,290 @@
+use crate::{Arena, Node, NodeId};
+use alloc::vec;
+use alloc::vec::Vec;
+use allocator_api2::alloc::{Allocator, Global};
+
+/// Tells `Arena::walk` how to carry on after a `Visitor` hook returns.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Control {
+    /// Carry on walking the tree.
+    Continue,
+    /// When returned from `Visitor::visit_enter`, skip the children of the
+    /// node. When returned from `Visitor::visit_leave`, skip the remaining
+    /// siblings of the node.
+    Skip,
+    /// Stop walking the tree straight away.
+    Stop,
+}
+
+/// Hooks called by `Arena::walk` as it enters and leaves every node of a
+/// tree.
+///
+/// Both hooks default to doing nothing and returning `Control::Continue`.
+/// `A` is the allocator of the arenas the visitor walks.
+pub trait Visitor<T, A: Allocator = Global> {
+    /// Called before any of the children of `node` are visited.
+    fn visit_enter(&mut self, node: &Node<T, A>) -> Control {
+        let _ = node;
+        Control::Continue
+    }
+
+    /// Called after all of the children of `node` have been visited, or
+    /// straight after `Visitor::visit_enter` if they were skipped.
+    fn visit_leave(&mut self, node: &Node<T, A>) -> Control {
+        let _ = node;
+        Control::Continue
+    }
+}
+
+impl<T, A> Arena<T, A>
+where
+    A: Allocator + Clone,
+{
+    /// Walks the tree starting at `root` depth-first, calling the hooks of
+    /// `visitor` as every node is entered and left.
+    ///
+    /// Returns `Control::Stop` if the walk was stopped by the visitor, or
+    /// `Control::Continue` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena, Control, Node, Visitor};
+    /// struct Leaves(Vec<&'static str>);
+    ///
+    /// impl Visitor<&'static str> for Leaves {
+    ///     fn visit_enter(&mut self, node: &Node<&'static str>) -> Control {
+    ///         if node.children().is_empty() {
+    ///             self.0.push(node.data());
+    ///         }
+    ///         Control::Continue
+    ///     }
+    /// }
+    ///
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, "1" => ["2", "3" => ["4"]]);
+    ///
+    /// let mut leaves = Leaves(vec![]);
+    /// arena.walk(root, &mut leaves);
+    /// assert_eq!(leaves.0, vec!["2", "4"]);
+    /// ```
+    pub fn walk<V>(&self, root: NodeId, visitor: &mut V) -> Control
+    where
+        V: Visitor<T, A> + ?Sized,
+    {
+        let mut stack = Vec::new();
+
+        match visitor.visit_enter(self.get(root)) {
+            Control::Continue => stack.push((root, 0)),
+            Control::Skip => return finish(visitor.visit_leave(self.get(root))),
+            Control::Stop => return Control::Stop,
+        }
+
+        while let Some((id, next)) = stack.last_mut() {
+            let node = self.get(*id);
+
+            let child = match node.children().get(*next) {
+                Some(&child) => child,
+                None => {
+                    stack.pop();
+                    match visitor.visit_leave(node) {
+                        Control::Continue => {},
+                        Control::Skip => skip_siblings(&mut stack),
+                        Control::Stop => return Control::Stop,
+                    }
+                    continue;
+                },
+            };
+
+            *next += 1;
+
+            match visitor.visit_enter(self.get(child)) {
+                Control::Continue => stack.push((child, 0)),
+                Control::Skip => match visitor.visit_leave(self.get(child)) {
+                    Control::Continue => {},
+                    Control::Skip => skip_siblings(&mut stack),
+                    Control::Stop => return Control::Stop,
+                },
+                Control::Stop => return Control::Stop,
+            }
+        }
+
+        Control::Continue
+    }
+
+    /// Computes a value for every node of the tree starting at `root` from
+    /// the bottom up, returning the value computed for `root`.
+    ///
+    /// `f` is called with each node and the values computed for its
+    /// children, in order. Children are always folded before their parent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use isaac::{tree, Arena};
+    /// let arena = &mut Arena::new();
+    /// let root = tree!(arena, 1 => [2, 3 => [4]]);
+    ///
+    /// // Sum up every value in the tree.
+    /// let sum = arena.fold(root, |node, children: Vec<i32>| {
+    ///     node.data() + children.iter().sum::<i32>()
+    /// });
+    /// assert_eq!(sum, 10);
+    /// ```
+    pub fn fold<U, F>(&self, root: NodeId, mut f: F) -> U
+    where
+        F: FnMut(&Node<T, A>, Vec<U>) -> U,
+    {
+        let mut values = Vec::new();
+        let mut stack = vec![(root, false)];
+
+        while let Some((id, visited)) = stack.pop() {
+            let node = self.get(id);
+
+            if visited {
+                let children = values.split_off(values.len() - node.children().len());
+                values.push(f(node, children));
+            } else {
+                stack.push((id, true));
+                stack.extend(node.children().iter().rev().map(|&child| (child, false)));
+            }
+        }
+
+        values.pop().expect("root should have been folded")
+    }
+}
+
+/// Maps the result of the last hook called by `Arena::walk` to its result.
+fn finish(control: Control) -> Control {
+    match control {
+        Control::Stop => Control::Stop,
+        _ => Control::Continue,
+    }
+}
+
+/// Skips the remaining children of the node at the top of the stack.
+fn skip_siblings(stack: &mut [(NodeId, usize)]) {
+    if let Some((_, next)) = stack.last_mut() {
+        *next = usize::MAX;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    /// Records every hook call, returning a scripted result for some nodes.
+    struct Recorder {
+        calls: Vec<String>,
+        enter: Vec<(i32, Control)>,
+        leave: Vec<(i32, Control)>,
+    }
+
+    impl Recorder {
+        fn new(enter: Vec<(i32, Control)>, leave: Vec<(i32, Control)>) -> Self {
+            Self { calls: vec![], enter, leave }
+        }
+
+        fn lookup(scripted: &[(i32, Control)], data: i32) -> Control {
+            scripted.iter()
+                .find(|&&(candidate, _)| candidate == data)
+                .map_or(Control::Continue, |&(_, control)| control)
+        }
+    }
+
+    impl Visitor<i32> for Recorder {
+        fn visit_enter(&mut self, node: &Node<i32>) -> Control {
+            self.calls.push(format!("+{}", node.data()));
+            Self::lookup(&self.enter, *node.data())
+        }
+
+        fn visit_leave(&mut self, node: &Node<i32>) -> Control {
+            self.calls.push(format!("-{}", node.data()));
+            Self::lookup(&self.leave, *node.data())
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+
+        let walk = |enter, leave| {
+            let mut recorder = Recorder::new(enter, leave);
+            let control = arena.walk(root, &mut recorder);
+            (recorder.calls.join(" "), control)
+        };
+
+        assert_eq!(
+            walk(vec![], vec![]),
+            ("+1 +2 +5 -5 +6 -6 -2 +3 -3 +4 +7 +8 -8 -7 -4 -1".to_string(), Control::Continue),
+        );
+        assert_eq!(
+            walk(vec![(2, Control::Skip)], vec![]),
+            ("+1 +2 -2 +3 -3 +4 +7 +8 -8 -7 -4 -1".to_string(), Control::Continue),
+        );
+        assert_eq!(
+            walk(vec![], vec![(5, Control::Skip), (2, Control::Skip)]),
+            ("+1 +2 +5 -5 -2 -1".to_string(), Control::Continue),
+        );
+        assert_eq!(
+            walk(vec![(3, Control::Stop)], vec![]),
+            ("+1 +2 +5 -5 +6 -6 -2 +3".to_string(), Control::Stop),
+        );
+        assert_eq!(
+            walk(vec![(1, Control::Skip)], vec![(1, Control::Stop)]),
+            ("+1 -1".to_string(), Control::Stop),
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let arena = &mut Arena::new();
+        let root = tree!(arena, "+" => ["1", "*" => ["2", "3"]]);
+
+        let value = arena.fold(root, |node, children: Vec<i32>| match *node.data() {
+            "+" => children.iter().sum(),
+            "*" => children.iter().product(),
+            leaf => leaf.parse().unwrap(),
+        });
+        assert_eq!(value, 7);
+
+        let sexpr = arena.fold(root, |node, children: Vec<String>| {
+            format!("({}{})", node.data(), children.concat())
+        });
+        assert_eq!(sexpr, arena.to_sexpr(root).replace(' ', ""));
+    }
+
+    #[test]
+    fn test_walk_edge_cases() {
+        let arena = &mut Arena::new();
+        let root = fixtures::sample(arena);
+
+        let walk = |start, enter, leave| {
+            let mut recorder = Recorder::new(enter, leave);
+            let control = arena.walk(start, &mut recorder);
+            (recorder.calls.join(" "), control)
+        };
+
+        // Stopping on the first node ends the walk before any leave hook.
+        assert_eq!(walk(root, vec![(1, Control::Stop)], vec![]), ("+1".to_string(), Control::Stop));
+
+        // Stopping while leaving a node skips the leave hooks of every open
+        // ancestor.
+        assert_eq!(
+            walk(root, vec![], vec![(5, Control::Stop)]),
+            ("+1 +2 +5 -5".to_string(), Control::Stop),
+        );
+
+        // Skipping a leaf is the same as continuing.
+        assert_eq!(walk(root, vec![(3, Control::Skip)], vec![]), walk(root, vec![], vec![]));
+
+        // A walk from a subtree never reaches its parent or siblings.
+        let four = fixtures::find(arena, 4);
+        assert_eq!(
+            walk(four, vec![], vec![(4, Control::Stop)]),
+            ("+4 +7 +8 -8 -7 -4".to_string(), Control::Stop),
+        );
+    }
+}